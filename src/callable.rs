@@ -1,32 +1,86 @@
 use std::fmt;
+use std::path::PathBuf;
 
 use crate::interpreter::Interpreter;
 use crate::parser::StmtFunction;
 use crate::value::{Er, Value};
 
-pub trait Callable: fmt::Debug {
-    fn artiy(&self) -> usize;
+// Number of arguments a callable accepts, max is None for variadic callables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arity {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn exact(n: usize) -> Self {
+        Arity {
+            min: n,
+            max: Some(n),
+        }
+    }
+
+    pub fn accepts(&self, n: usize) -> bool {
+        n >= self.min && self.max.is_none_or(|max| n <= max)
+    }
+
+    // Arity of taking the parameters of self followed by those of other
+    pub fn then(self, other: Arity) -> Self {
+        Arity {
+            min: self.min + other.min,
+            max: self.max.zip(other.max).map(|(lhs, rhs)| lhs + rhs),
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", max),
+            Some(max) => write!(f, "{} to {}", self.min, max),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
+}
+
+// Display is how the callable prints in Lox
+pub trait Callable: fmt::Debug + fmt::Display {
+    fn name(&self) -> &str;
+    fn arity(&self) -> Arity;
     fn call(&self, _: &mut Interpreter, _: Vec<Value>) -> Result<Value, Er>;
 }
 
 #[derive(Debug, Clone)]
 pub struct Function {
     pub declaration: StmtFunction,
+    // file the function was declared in, its body sees that file's globals
+    pub module: PathBuf,
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.name())
+    }
 }
 
 impl Callable for Function {
-    fn artiy(&self) -> usize {
-        self.declaration.params.len()
+    fn name(&self) -> &str {
+        self.declaration.name.name()
+    }
+    fn arity(&self) -> Arity {
+        Arity::exact(self.declaration.params.len())
     }
     fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Er> {
-        let mut temp = std::mem::take(&mut interpreter.envs);
+        let locals = interpreter.envs.take_locals();
+        let caller_module = interpreter.enter_module(&self.module)?;
         interpreter.envs.push_default();
         for (param, arg) in self.declaration.params.iter().zip(args.iter()) {
-            interpreter.envs.define(param, arg.clone());
+            interpreter.envs.define(param.name(), arg.clone());
         }
         let ret = interpreter.eval_block(&self.declaration.body);
         interpreter.envs.pop()?;
-        std::mem::swap(&mut interpreter.envs, &mut temp);
+        interpreter.enter_module(&caller_module)?;
+        interpreter.envs.restore_locals(locals);
         match ret {
             Err(Er::Return(r)) => Ok(r),
             Err(e) => Err(e),
@@ -0,0 +1,148 @@
+use std::cmp::Reverse;
+use std::iter::Peekable;
+
+use crate::error::Diagnostic;
+use crate::parser::{self, NodeSpan, Stmt};
+use crate::scanner::{self, Lexeme};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    // the whole source
+    Program,
+    // the tokens of a statement that didn't parse
+    Error,
+
+    // Statements and their parts
+    Var,
+    Function,
+    Parameters,
+    Import,
+    Block,
+    ExprStmt,
+    Print,
+    Return,
+    If,
+    While,
+    For,
+    Throw,
+    Try,
+    Catch,
+    Finally,
+    Match,
+    MatchArm,
+
+    // Expressions and their parts
+    Assign,
+    Conditional,
+    Logical,
+    Binary,
+    Unary,
+    Postfix,
+    Call,
+    Arguments,
+    Get,
+    Grouping,
+    Literal,
+    Variable,
+    Interpolation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    Node(Node),
+    Lexeme(Lexeme),
+}
+
+// Part of the source, with the whitespace and comments between its tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+}
+
+impl Node {
+    // Every lexeme of the node, together they are the source text as written
+    pub fn lexemes(&self) -> Box<dyn Iterator<Item = &Lexeme> + '_> {
+        Box::new(self.children.iter().flat_map(|child| match child {
+            Element::Node(node) => node.lexemes(),
+            Element::Lexeme(lexeme) => Box::new(std::iter::once(lexeme)),
+        }))
+    }
+}
+
+// Concrete syntax tree of a source, keeping every byte of it, so tools can
+// work with the text as written
+#[derive(Debug, Clone)]
+pub struct SyntaxTree {
+    pub root: Node,
+    // problems of the scanner and parser, the text they are about is in
+    // Error lexemes and nodes
+    pub errors: Vec<Diagnostic>,
+    statements: Vec<Stmt>,
+}
+
+impl SyntaxTree {
+    pub fn parse(source: &str) -> SyntaxTree {
+        let lexemes = scanner::scan_lexemes(source);
+        let (tokens, mut errors, indices) = scanner::split_lexemes(&lexemes);
+        let (statements, parse_errors, spans) = parser::parse_spans(tokens);
+        errors.extend(parse_errors);
+        // outer nodes before the ones they contain, spans are recorded inner first
+        let mut spans: Vec<(usize, NodeSpan)> = spans.into_iter().enumerate().collect();
+        spans.sort_by_key(|&(i, span)| (span.start, Reverse(span.end), Reverse(i)));
+        let mut spans = spans
+            .into_iter()
+            .map(|(_, span)| (span.kind, indices[span.start], indices[span.end - 1] + 1))
+            .peekable();
+
+        let root = build(NodeKind::Program, 0, lexemes.len(), &lexemes, &mut spans);
+        SyntaxTree {
+            root,
+            errors,
+            statements,
+        }
+    }
+
+    // The abstract syntax tree the interpreter runs, parsed along with the
+    // nodes, without the statements in Error nodes
+    pub fn statements(&self) -> &[Stmt] {
+        &self.statements
+    }
+}
+
+// The node of the lexemes from lo to hi, spans being its descendants as
+// ranges of lexemes, ordered by start
+fn build<I>(
+    kind: NodeKind,
+    lo: usize,
+    hi: usize,
+    lexemes: &[Lexeme],
+    spans: &mut Peekable<I>,
+) -> Node
+where
+    I: Iterator<Item = (NodeKind, usize, usize)>,
+{
+    let mut children = vec![];
+    let mut i = lo;
+    while i < hi {
+        // spans crossing the ones already built can't be nested, drop them
+        while spans
+            .peek()
+            .is_some_and(|&(_, start, end)| start < i || start == i && end > hi)
+        {
+            spans.next();
+        }
+        match spans.peek() {
+            Some(&(kind, start, end)) if start == i => {
+                spans.next();
+                children.push(Element::Node(build(kind, start, end, lexemes, spans)));
+                i = end;
+            }
+            _ => {
+                children.push(Element::Lexeme(lexemes[i].clone()));
+                i += 1;
+            }
+        }
+    }
+    Node { kind, children }
+}
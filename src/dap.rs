@@ -0,0 +1,458 @@
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::debug::{Breakpoint, Debugger, Frontend, Paused, Reason, Resume};
+use crate::error::Error;
+use crate::globals::Globals;
+use crate::json::Json;
+use crate::lox::Lox;
+use crate::lsp::{read_message, write_message};
+use crate::value::Value;
+
+// Programs have a single thread
+const THREAD_ID: usize = 1;
+
+// Variable references of the scopes of the paused call
+const LOCALS: usize = 1;
+const GLOBALS: usize = 2;
+
+// Both ends of the protocol, shared by the server, the frontend and the output
+// of the program
+struct Connection {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    seq: usize,
+    // the client disconnected or closed the input, there is nothing left to serve
+    closed: bool,
+}
+
+impl Connection {
+    // The next request, None at the end of the input
+    fn read(&mut self) -> io::Result<Option<Json>> {
+        loop {
+            let body = match read_message(&mut self.input)? {
+                Some(body) => body,
+                None => {
+                    self.closed = true;
+                    return Ok(None);
+                }
+            };
+            // there is no request to answer for messages that don't parse
+            if let Ok(message) = Json::parse(&body) {
+                if message.get("type").as_str() == Some("request") {
+                    return Ok(Some(message));
+                }
+            }
+        }
+    }
+
+    fn send(&mut self, kind: &str, members: Vec<(&str, Json)>) -> io::Result<()> {
+        self.seq += 1;
+        let mut message = vec![("seq", Json::from(self.seq)), ("type", Json::from(kind))];
+        message.extend(members);
+        write_message(&mut self.output, &Json::object(message))
+    }
+
+    fn respond(&mut self, request: &Json, result: Result<Json, String>) -> io::Result<()> {
+        let mut members = vec![
+            ("request_seq", request.get("seq").clone()),
+            ("command", request.get("command").clone()),
+            ("success", Json::from(result.is_ok())),
+        ];
+        match result {
+            Ok(Json::Null) => {}
+            Ok(body) => members.push(("body", body)),
+            Err(message) => members.push(("message", Json::from(message))),
+        }
+        self.send("response", members)
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        let mut members = vec![("event", Json::from(event))];
+        if body != Json::Null {
+            members.push(("body", body));
+        }
+        self.send("event", members)
+    }
+}
+
+// Output of the program, sent as output events of a category a line at a time
+struct Output {
+    connection: Rc<RefCell<Connection>>,
+    category: &'static str,
+    line: String,
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.push_str(&String::from_utf8_lossy(buf));
+        if self.line.ends_with('\n') {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+        let body = Json::object(vec![
+            ("category", Json::from(self.category)),
+            ("output", Json::from(std::mem::take(&mut self.line))),
+        ]);
+        self.connection.borrow_mut().event("output", body)
+    }
+}
+
+// Output without a line break at the end is sent once the program is done
+impl Drop for Output {
+    fn drop(&mut self) {
+        self.flush().ok();
+    }
+}
+
+fn threads() -> Json {
+    let thread = Json::object(vec![
+        ("id", Json::from(THREAD_ID)),
+        ("name", Json::from("main")),
+    ]);
+    Json::object(vec![("threads", Json::Array(vec![thread]))])
+}
+
+fn capabilities() -> Json {
+    Json::object(vec![
+        ("supportsConfigurationDoneRequest", Json::from(true)),
+        ("supportsConditionalBreakpoints", Json::from(true)),
+        ("supportsEvaluateForHovers", Json::from(true)),
+        ("supportsTerminateRequest", Json::from(true)),
+    ])
+}
+
+fn source(path: &Path) -> Json {
+    let name = path
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+    Json::object(vec![
+        ("name", Json::from(name)),
+        ("path", Json::from(path.display().to_string())),
+    ])
+}
+
+// Replace the breakpoints of a file with those of a setBreakpoints request
+fn set_breakpoints(breakpoints: &mut Vec<Breakpoint>, arguments: &Json) -> Result<Json, String> {
+    let path = arguments.get("source").get("path").as_str();
+    let path = Path::new(path.ok_or("Missing source path.")?);
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+    breakpoints.retain(|breakpoint| breakpoint.path != path);
+
+    let mut verified = vec![];
+    for breakpoint in arguments.get("breakpoints").as_array().unwrap_or(&[]) {
+        let line = breakpoint.get("line").as_usize().ok_or("Missing line.")?;
+        // editors send empty conditions for plain breakpoints
+        let condition = breakpoint.get("condition").as_str();
+        let condition = condition
+            .filter(|c| !c.trim().is_empty())
+            .map(str::to_owned);
+        breakpoints.push(Breakpoint {
+            path: path.clone(),
+            line,
+            condition,
+        });
+        verified.push(Json::object(vec![
+            ("verified", Json::from(true)),
+            ("line", Json::from(line)),
+        ]));
+    }
+    Ok(Json::object(vec![("breakpoints", Json::Array(verified))]))
+}
+
+fn variables(variables: impl IntoIterator<Item = (String, Value)>) -> Json {
+    let variables = variables.into_iter().map(|(name, value)| {
+        Json::object(vec![
+            ("name", Json::from(name)),
+            ("value", Json::from(value.to_string())),
+            ("type", Json::from(value.type_name())),
+            ("variablesReference", Json::from(0usize)),
+        ])
+    });
+    Json::object(vec![("variables", Json::Array(variables.collect()))])
+}
+
+// Answers the requests of the client while the program is paused
+struct DapFrontend {
+    connection: Rc<RefCell<Connection>>,
+    // names of the standard library, left out of the globals shown
+    builtins: Vec<String>,
+}
+
+impl DapFrontend {
+    // Handle a request, returning how to resume if it does
+    fn request(
+        &mut self,
+        paused: &mut Paused,
+        request: &Json,
+    ) -> (Result<Json, String>, Option<Resume>) {
+        let arguments = request.get("arguments");
+        let resume = |resume| (Ok(Json::Null), Some(resume));
+        let result = match request.get("command").as_str().unwrap_or("") {
+            "continue" => {
+                let body = Json::object(vec![("allThreadsContinued", Json::from(true))]);
+                return (Ok(body), Some(Resume::Continue));
+            }
+            "next" => return resume(Resume::StepOver),
+            "stepIn" => return resume(Resume::StepIn),
+            "stepOut" => return resume(Resume::StepOut),
+            "disconnect" => {
+                self.connection.borrow_mut().closed = true;
+                return resume(Resume::Quit);
+            }
+            "terminate" => return resume(Resume::Quit),
+            // it is paused already
+            "pause" | "setExceptionBreakpoints" | "configurationDone" => Ok(Json::Null),
+            "threads" => Ok(threads()),
+            "setBreakpoints" => set_breakpoints(paused.breakpoints, arguments),
+            "stackTrace" => {
+                let frames = paused.stack().into_iter().enumerate().map(|(id, frame)| {
+                    Json::object(vec![
+                        ("id", Json::from(id)),
+                        ("name", Json::from(frame.name)),
+                        ("source", source(&frame.path)),
+                        ("line", Json::from(frame.line)),
+                        ("column", Json::from(1usize)),
+                    ])
+                });
+                let frames: Vec<_> = frames.collect();
+                Ok(Json::object(vec![
+                    ("totalFrames", Json::from(frames.len())),
+                    ("stackFrames", Json::Array(frames)),
+                ]))
+            }
+            "scopes" => {
+                let scope = |name: &str, reference: usize| {
+                    Json::object(vec![
+                        ("name", Json::from(name)),
+                        ("variablesReference", Json::from(reference)),
+                        ("expensive", Json::from(false)),
+                    ])
+                };
+                // only the variables of the paused call are at hand
+                let scopes = match arguments.get("frameId").as_usize() {
+                    Some(0) | None => vec![scope("Locals", LOCALS), scope("Globals", GLOBALS)],
+                    Some(_) => vec![],
+                };
+                Ok(Json::object(vec![("scopes", Json::Array(scopes))]))
+            }
+            "variables" => {
+                let mut scopes = paused.scopes();
+                let globals = scopes.pop().unwrap_or_default();
+                match arguments.get("variablesReference").as_usize() {
+                    Some(LOCALS) => {
+                        // names of outer scopes are shadowed by those of inner ones
+                        let mut locals: Vec<(String, Value)> = vec![];
+                        for (name, value) in scopes.into_iter().flatten() {
+                            if !locals.iter().any(|(local, _)| *local == name) {
+                                locals.push((name, value));
+                            }
+                        }
+                        Ok(variables(locals))
+                    }
+                    Some(GLOBALS) => Ok(variables(
+                        globals
+                            .into_iter()
+                            .filter(|(name, _)| !self.builtins.contains(name)),
+                    )),
+                    _ => Err("Unknown variables reference.".to_owned()),
+                }
+            }
+            "evaluate" => {
+                let expression = arguments.get("expression").as_str().unwrap_or("");
+                paused.evaluate(expression).map(|value| {
+                    Json::object(vec![
+                        ("result", Json::from(value.to_string())),
+                        ("type", Json::from(value.type_name())),
+                        ("variablesReference", Json::from(0usize)),
+                    ])
+                })
+            }
+            command => Err(format!("Unsupported request '{}'.", command)),
+        };
+        (result, None)
+    }
+}
+
+impl Frontend for DapFrontend {
+    fn paused(&mut self, paused: &mut Paused<'_>) -> Resume {
+        let reason = match paused.reason {
+            Reason::Entry => "entry",
+            Reason::Breakpoint => "breakpoint",
+            Reason::Step => "step",
+        };
+        let body = Json::object(vec![
+            ("reason", Json::from(reason)),
+            ("threadId", Json::from(THREAD_ID)),
+            ("allThreadsStopped", Json::from(true)),
+        ]);
+        if self.connection.borrow_mut().event("stopped", body).is_err() {
+            return Resume::Quit;
+        }
+        loop {
+            // not borrowed while handling requests, evaluated code may print
+            let request = self.connection.borrow_mut().read();
+            let request = match request {
+                Ok(Some(request)) => request,
+                Ok(None) | Err(_) => return Resume::Quit,
+            };
+            let (result, resume) = self.request(paused, &request);
+            if self
+                .connection
+                .borrow_mut()
+                .respond(&request, result)
+                .is_err()
+            {
+                return Resume::Quit;
+            }
+            if let Some(resume) = resume {
+                return resume;
+            }
+        }
+    }
+}
+
+// What a launch request asks for
+struct Launch {
+    path: PathBuf,
+    source: String,
+    stop_on_entry: bool,
+}
+
+fn launch(arguments: &Json) -> Result<Launch, String> {
+    let path = arguments
+        .get("program")
+        .as_str()
+        .ok_or("Missing program.")?;
+    let source =
+        std::fs::read_to_string(path).map_err(|e| format!("Could not read '{}': {}.", path, e))?;
+    Ok(Launch {
+        path: PathBuf::from(path),
+        source,
+        stop_on_entry: arguments.get("stopOnEntry").as_bool().unwrap_or(false),
+    })
+}
+
+// Serve the Debug Adapter Protocol over a pair of streams, usually stdin and
+// stdout. Runs the program of the launch request once the client is done
+// configuring, then serves until the client disconnects or closes the input
+pub fn serve_dap(input: impl BufRead + 'static, output: impl Write + 'static) -> io::Result<()> {
+    let connection = Rc::new(RefCell::new(Connection {
+        input: Box::new(input),
+        output: Box::new(output),
+        seq: 0,
+        closed: false,
+    }));
+
+    let mut breakpoints = vec![];
+    let mut launched = None;
+    let mut configured = false;
+    let launch = loop {
+        if configured {
+            if let Some(launch) = launched.take() {
+                break launch;
+            }
+        }
+        let request = connection.borrow_mut().read()?;
+        let request = match request {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+        let arguments = request.get("arguments");
+        let mut connection = connection.borrow_mut();
+        let result = match request.get("command").as_str().unwrap_or("") {
+            "initialize" => {
+                connection.respond(&request, Ok(capabilities()))?;
+                connection.event("initialized", Json::Null)?;
+                continue;
+            }
+            "launch" => launch(arguments).map(|launch| {
+                launched = Some(launch);
+            }),
+            "configurationDone" => {
+                configured = true;
+                Ok(())
+            }
+            "setBreakpoints" => {
+                let result = set_breakpoints(&mut breakpoints, arguments);
+                connection.respond(&request, result)?;
+                continue;
+            }
+            "setExceptionBreakpoints" => Ok(()),
+            "threads" => {
+                connection.respond(&request, Ok(threads()))?;
+                continue;
+            }
+            "disconnect" | "terminate" => {
+                connection.respond(&request, Ok(Json::Null))?;
+                return Ok(());
+            }
+            command => Err(format!("Unsupported request '{}'.", command)),
+        };
+        connection.respond(&request, result.map(|_| Json::Null))?;
+    };
+
+    let Launch {
+        path,
+        source,
+        stop_on_entry,
+    } = launch;
+    let mut lox = Lox::with_path(&path);
+    let output = |category| Output {
+        connection: connection.clone(),
+        category,
+        line: String::new(),
+    };
+    lox.set_output(output("stdout"));
+    lox.set_error_output(output("stderr"));
+    // the input carries the protocol
+    lox.set_input(io::empty());
+    let builtins = Globals::new().functions;
+    let frontend = DapFrontend {
+        connection: connection.clone(),
+        builtins: builtins.into_iter().map(|(name, _)| name).collect(),
+    };
+    lox.set_debugger(Debugger::new(frontend, breakpoints, stop_on_entry));
+
+    let exit_code = match lox.run_source(&source) {
+        Ok(()) | Err(Error::Stopped { .. }) => 0usize,
+        Err(e) => {
+            let body = Json::object(vec![
+                ("category", Json::from("stderr")),
+                ("output", Json::from(format!("{}\n", e))),
+            ]);
+            connection.borrow_mut().event("output", body)?;
+            1
+        }
+    };
+    drop(lox);
+    let mut connection = connection.borrow_mut();
+    let body = Json::object(vec![("exitCode", Json::from(exit_code))]);
+    connection.event("exited", body)?;
+    connection.event("terminated", Json::Null)?;
+
+    while !connection.closed {
+        let request = match connection.read()? {
+            Some(request) => request,
+            None => break,
+        };
+        let result = match request.get("command").as_str().unwrap_or("") {
+            "disconnect" => {
+                connection.closed = true;
+                Ok(Json::Null)
+            }
+            "threads" => Ok(Json::object(vec![("threads", Json::Array(vec![]))])),
+            _ => Err("The program has ended.".to_owned()),
+        };
+        connection.respond(&request, result)?;
+    }
+    Ok(())
+}
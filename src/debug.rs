@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::highlight::highlight_ansi;
+use crate::interpreter::{is_truthy, Interpreter};
+use crate::parser::{self, Stmt};
+use crate::scanner;
+use crate::value::{describe, Er, Value};
+
+// Name of the top level in call stacks
+const TOP_LEVEL: &str = "<script>";
+
+// Called by the interpreter before each statement, which it runs once this returns
+pub trait DebugHook {
+    fn statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+        line: usize,
+    ) -> Result<(), Er>;
+}
+
+// A call in progress, with the file and line it was made from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub name: String,
+    pub path: PathBuf,
+    pub line: usize,
+}
+
+// The debugger of a run, and the calls it shows. Calls are only tracked while
+// there is a debugger
+#[derive(Default)]
+pub struct Debugging {
+    pub hook: Option<Box<dyn DebugHook>>,
+    pub frames: Vec<Frame>,
+}
+
+impl fmt::Debug for Debugging {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debugging")
+            .field("frames", &self.frames)
+            .finish()
+    }
+}
+
+// Pauses at statements starting on the line, when the condition, if any, is
+// truthy. A condition failing to evaluate pauses too
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub path: PathBuf,
+    pub line: usize,
+    pub condition: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    // before the first statement
+    Entry,
+    Breakpoint,
+    Step,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Reason::Entry => "entry",
+            Reason::Breakpoint => "breakpoint",
+            Reason::Step => "step",
+        })
+    }
+}
+
+// How to go on from a pause
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    // until a breakpoint
+    Continue,
+    // to the next statement, also inside of calls
+    StepIn,
+    // to the next statement of this call or its callers
+    StepOver,
+    // to the next statement of a caller
+    StepOut,
+    // end the run
+    Quit,
+}
+
+// The program as it is paused, for frontends to look at
+pub struct Paused<'a> {
+    pub reason: Reason,
+    pub line: usize,
+    // may be changed while paused
+    pub breakpoints: &'a mut Vec<Breakpoint>,
+    interpreter: &'a mut Interpreter,
+}
+
+impl Paused<'_> {
+    // The file of the statement paused at
+    pub fn path(&self) -> &Path {
+        &self.interpreter.module
+    }
+
+    // The calls in progress, innermost first, with the line each is at
+    pub fn stack(&self) -> Vec<Frame> {
+        let frames = &self.interpreter.debugging.frames;
+        let mut stack = vec![];
+        let (mut path, mut line) = (self.path().to_owned(), self.line);
+        for frame in frames.iter().rev() {
+            stack.push(Frame {
+                name: frame.name.clone(),
+                path,
+                line,
+            });
+            path = frame.path.clone();
+            line = frame.line;
+        }
+        stack.push(Frame {
+            name: TOP_LEVEL.to_owned(),
+            path,
+            line,
+        });
+        stack
+    }
+
+    // Variables of the scopes of the current call, innermost first and the
+    // globals of its file last
+    pub fn scopes(&self) -> Vec<Vec<(String, Value)>> {
+        self.interpreter
+            .envs
+            .scopes()
+            .rev()
+            .map(|scope| {
+                scope
+                    .values()
+                    .into_iter()
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Value of an expression in the scopes of the current call
+    pub fn evaluate(&mut self, source: &str) -> Result<Value, String> {
+        evaluate(self.interpreter, source)
+    }
+}
+
+fn evaluate(interpreter: &mut Interpreter, source: &str) -> Result<Value, String> {
+    let (tokens, errors) = scanner::scan_tokens(source.chars());
+    if let Some(error) = errors.first() {
+        return Err(error.message.clone());
+    }
+    let expr = parser::parse_expression(tokens).map_err(|e| e.message)?;
+
+    // errors of the expression are about its own line, not the paused one
+    let line = interpreter.line;
+    let res = interpreter.eval_expr(&expr);
+    interpreter.line = line;
+    res.map_err(|er| match er {
+        Er::Throw(Value::Error(e)) => e.message.clone(),
+        Er::Throw(value) => value.to_string(),
+        Er::Code(code) => describe(code).to_owned(),
+        Er::Limit(limit) => format!("Resource limit exceeded, used {}.", limit),
+        Er::Diverged(message) => message,
+        Er::Return(_) | Er::Stopped => "Stopped.".to_owned(),
+    })
+}
+
+// What pauses the program and shows it to the user
+pub trait Frontend {
+    // Called whenever the program pauses, until it is resumed
+    fn paused(&mut self, paused: &mut Paused<'_>) -> Resume;
+}
+
+// Where to pause next, besides breakpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Entry,
+    Any,
+    // at a call depth of at most the given one
+    Within(usize),
+}
+
+// Pauses a run at breakpoints and steps, for a frontend to drive
+pub struct Debugger<F: Frontend> {
+    frontend: F,
+    breakpoints: Vec<Breakpoint>,
+    step: Option<Step>,
+    // the statement last seen, with its file, line and call depth. Statements
+    // on the same line as the previous one don't pause again, but repeating one does
+    last: Option<(PathBuf, usize, usize, *const Stmt)>,
+    quit: bool,
+}
+
+impl<F: Frontend> Debugger<F> {
+    // Debugger pausing before the first statement if stop_on_entry
+    pub fn new(frontend: F, breakpoints: Vec<Breakpoint>, stop_on_entry: bool) -> Self {
+        Debugger {
+            frontend,
+            breakpoints,
+            step: if stop_on_entry {
+                Some(Step::Entry)
+            } else {
+                None
+            },
+            last: None,
+            quit: false,
+        }
+    }
+
+    fn breakpoint(&self, interpreter: &mut Interpreter, line: usize) -> bool {
+        let breakpoint = self
+            .breakpoints
+            .iter()
+            .find(|breakpoint| breakpoint.line == line && breakpoint.path == interpreter.module);
+        match breakpoint.map(|breakpoint| &breakpoint.condition) {
+            None => false,
+            Some(None) => true,
+            Some(Some(condition)) => {
+                evaluate(interpreter, condition).map_or(true, |value| is_truthy(&value))
+            }
+        }
+    }
+}
+
+impl<F: Frontend> DebugHook for Debugger<F> {
+    fn statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+        line: usize,
+    ) -> Result<(), Er> {
+        // finally blocks still run after quitting, they stop at once too
+        if self.quit {
+            return Err(Er::Stopped);
+        }
+
+        let depth = interpreter.debugging.frames.len();
+        let at = (interpreter.module.clone(), line, depth, stmt as *const Stmt);
+        let moved = self
+            .last
+            .as_ref()
+            .is_none_or(|last| (&last.0, last.1, last.2) != (&at.0, at.1, at.2) || last.3 == at.3);
+        self.last = Some(at);
+        if !moved {
+            return Ok(());
+        }
+
+        let reason = match self.step {
+            Some(Step::Entry) => Some(Reason::Entry),
+            Some(Step::Any) => Some(Reason::Step),
+            Some(Step::Within(max)) if depth <= max => Some(Reason::Step),
+            _ => None,
+        };
+        let reason = match reason {
+            Some(reason) => reason,
+            None if self.breakpoint(interpreter, line) => Reason::Breakpoint,
+            None => return Ok(()),
+        };
+
+        let mut paused = Paused {
+            reason,
+            line,
+            breakpoints: &mut self.breakpoints,
+            interpreter,
+        };
+        self.step = match self.frontend.paused(&mut paused) {
+            Resume::Continue => None,
+            Resume::StepIn => Some(Step::Any),
+            Resume::StepOver => Some(Step::Within(depth)),
+            Resume::StepOut if depth == 0 => None,
+            Resume::StepOut => Some(Step::Within(depth - 1)),
+            Resume::Quit => {
+                self.quit = true;
+                return Err(Er::Stopped);
+            }
+        };
+        Ok(())
+    }
+}
+
+const HELP: &str = "Commands:
+  break [FILE:]LINE [if EXPR]  pause at LINE, only when EXPR is truthy if given
+  delete [FILE:]LINE           remove the breakpoint at LINE
+  breakpoints                  list the breakpoints
+  continue, c                  run until a breakpoint
+  step, s                      run to the next statement, into calls
+  next, n                      run to the next statement, over calls
+  out, o                       run until the current call returns
+  print EXPR, p EXPR           evaluate EXPR in the current call
+  locals                       show the variables of the current call
+  globals                      show the global variables
+  backtrace, bt                show the call stack
+  list, l                      show the source around the current line
+  quit, q                      end the run
+  help, h                      show this";
+
+// A debugger driven by commands typed on a terminal, like `break 12` and `next`
+pub struct Console<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    // lines of the files shown so far
+    sources: HashMap<PathBuf, Vec<String>>,
+    colors: bool,
+}
+
+impl<R: BufRead, W: Write> Console<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Console {
+            input,
+            output,
+            sources: HashMap::new(),
+            colors: false,
+        }
+    }
+
+    // Show source lines highlighted with ANSI colours
+    pub fn set_colors(&mut self, colors: bool) {
+        self.colors = colors;
+    }
+
+    fn source_line(&mut self, path: &Path, line: usize) -> Option<String> {
+        let colors = self.colors;
+        let lines = self.sources.entry(path.to_owned()).or_insert_with(|| {
+            let text = std::fs::read_to_string(path).unwrap_or_default();
+            let text = if colors { highlight_ansi(&text) } else { text };
+            text.lines().map(str::to_owned).collect()
+        });
+        lines.get(line.checked_sub(1)?).cloned()
+    }
+
+    fn show_line(&mut self, path: &Path, line: usize, current: bool) {
+        if let Some(text) = self.source_line(path, line) {
+            let marker = if current { '>' } else { ' ' };
+            writeln!(self.output, "{} {:>4} | {}", marker, line, text).ok();
+        }
+    }
+
+    fn show_variables(&mut self, variables: &[(String, Value)]) {
+        for (name, value) in variables {
+            writeln!(self.output, "  {} = {}", name, value).ok();
+        }
+    }
+
+    // `[FILE:]LINE`, files being relative to the working directory
+    fn location(&self, paused: &Paused, text: &str) -> Option<(PathBuf, usize)> {
+        let (path, line) = match text.rsplit_once(':') {
+            Some((path, line)) => {
+                let path = Path::new(path);
+                (
+                    path.canonicalize().unwrap_or_else(|_| path.to_owned()),
+                    line,
+                )
+            }
+            None => (paused.path().to_owned(), text),
+        };
+        Some((path, line.trim().parse().ok()?))
+    }
+
+    // Handle a command, returning how to resume if it does
+    fn command(&mut self, paused: &mut Paused, line: &str) -> Option<Resume> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command {
+            "continue" | "c" => return Some(Resume::Continue),
+            "step" | "s" => return Some(Resume::StepIn),
+            "next" | "n" => return Some(Resume::StepOver),
+            "out" | "o" => return Some(Resume::StepOut),
+            "quit" | "q" => return Some(Resume::Quit),
+            "break" | "b" => {
+                let (location, condition) = match rest.split_once(" if ") {
+                    Some((location, condition)) => (location, Some(condition.trim().to_owned())),
+                    None => (rest, None),
+                };
+                match self.location(paused, location) {
+                    Some((path, line)) => {
+                        paused
+                            .breakpoints
+                            .retain(|b| (&b.path, b.line) != (&path, line));
+                        writeln!(self.output, "Breakpoint at {}:{}.", path.display(), line).ok();
+                        paused.breakpoints.push(Breakpoint {
+                            path,
+                            line,
+                            condition,
+                        });
+                    }
+                    None => {
+                        writeln!(self.output, "Expected a line number.").ok();
+                    }
+                }
+            }
+            "delete" | "d" => match self.location(paused, rest) {
+                Some((path, line)) => {
+                    let count = paused.breakpoints.len();
+                    paused
+                        .breakpoints
+                        .retain(|b| (&b.path, b.line) != (&path, line));
+                    if paused.breakpoints.len() == count {
+                        writeln!(self.output, "No breakpoint at line {}.", line).ok();
+                    }
+                }
+                None => {
+                    writeln!(self.output, "Expected a line number.").ok();
+                }
+            },
+            "breakpoints" => {
+                for breakpoint in paused.breakpoints.iter() {
+                    let condition = breakpoint.condition.as_ref();
+                    let condition = condition.map_or(String::new(), |c| format!(" if {}", c));
+                    let path = breakpoint.path.display();
+                    writeln!(self.output, "  {}:{}{}", path, breakpoint.line, condition).ok();
+                }
+            }
+            "print" | "p" => match paused.evaluate(rest) {
+                Ok(value) => {
+                    writeln!(self.output, "{}", value).ok();
+                }
+                Err(message) => {
+                    writeln!(self.output, "Error: {}", message).ok();
+                }
+            },
+            "locals" => {
+                let scopes = paused.scopes();
+                // the last scope holds the globals
+                for scope in &scopes[..scopes.len().saturating_sub(1)] {
+                    self.show_variables(scope);
+                }
+            }
+            "globals" => {
+                let builtins = crate::globals::Globals::new().functions;
+                let scopes = paused.scopes();
+                let globals = scopes.last().map_or(&[][..], |globals| globals);
+                let globals: Vec<_> = globals
+                    .iter()
+                    .filter(|(name, _)| !builtins.iter().any(|(builtin, _)| builtin == name))
+                    .cloned()
+                    .collect();
+                self.show_variables(&globals);
+            }
+            "backtrace" | "bt" => {
+                for (i, frame) in paused.stack().iter().enumerate() {
+                    let path = frame.path.display();
+                    writeln!(
+                        self.output,
+                        "  #{} {} at {}:{}",
+                        i, frame.name, path, frame.line
+                    )
+                    .ok();
+                }
+            }
+            "list" | "l" => {
+                let path = paused.path().to_owned();
+                for line in paused.line.saturating_sub(3).max(1)..=paused.line + 3 {
+                    self.show_line(&path, line, line == paused.line);
+                }
+            }
+            "help" | "h" => {
+                writeln!(self.output, "{}", HELP).ok();
+            }
+            "" => {}
+            _ => {
+                writeln!(self.output, "Unknown command '{}', see help.", command).ok();
+            }
+        }
+        None
+    }
+}
+
+impl<R: BufRead, W: Write> Frontend for Console<R, W> {
+    fn paused(&mut self, paused: &mut Paused<'_>) -> Resume {
+        let path = paused.path().to_owned();
+        writeln!(
+            self.output,
+            "Paused at {}:{} ({}).",
+            path.display(),
+            paused.line,
+            paused.reason
+        )
+        .ok();
+        self.show_line(&path, paused.line, true);
+        loop {
+            write!(self.output, "(lorx) ").ok();
+            self.output.flush().ok();
+            let mut line = String::new();
+            // the end of the input ends the run, like quit
+            match self.input.read_line(&mut line) {
+                Ok(0) | Err(_) => return Resume::Quit,
+                Ok(_) => {}
+            }
+            if let Some(resume) = self.command(paused, line.trim()) {
+                return resume;
+            }
+        }
+    }
+}
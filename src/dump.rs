@@ -0,0 +1,735 @@
+// Dumps of the tokens and syntax tree of a source, for tools building on the
+// front end of lorx. The JSON documents are objects with a "version", which is
+// SYNTAX_VERSION, and:
+//
+//   tokens: {"version", "tokens": [token]}, every lexeme but whitespace as
+//     {"kind", "text", "span"}. The kind is the name of the token type, like
+//     "left_paren", "identifier" or "while", or "comment" or "error". Numbers and
+//     strings also have a "value", the number or the text without quotes and
+//     escapes, errors a "message"
+//   ast: {"version", "statements": [node]}, a node being {"type", "span"} and
+//     the members of its type, nodes in capitals and names as strings:
+//       Expression: expression          Print: expression
+//       Var: name, initializer          Return: value
+//       Function: name, params, body    Throw: value
+//       Block: statements               If: condition, then, else?
+//       While: condition, body          Import: path, name
+//       Try: body, catch?: {name, body}, finally?
+//       Match: subject, arms: [{patterns, guard?, body}], a pattern being
+//         {"type": "ValuePattern", value}, {"type": "BindingPattern", name} or
+//         {"type": "DefaultPattern"}
+//       Literal: value                  Variable: name
+//       Assign: name, value             Set: object, name, value
+//       Unary: operator, operand        Postfix: operator, operand
+//       Binary: operator, left, right   Logical: operator, left, right
+//       Grouping: expression            Stringify: expression
+//       Call: callee, arguments         Get: object, name
+//       Conditional: condition, then, else
+//     with bodies and statements being lists of nodes, and members marked ?
+//     being null when left out
+//
+// A span is {"start", "end"} of positions {"offset", "line", "col"}, the end
+// being just after the text. Offsets count bytes from 0, lines and columns
+// count from 1, columns in characters.
+//
+// The tree is the one the interpreter runs, so `for` loops are blocks with a
+// while loop, `a += b` is `a = a + b` and interpolated strings are
+// concatenations of Stringify nodes. Nodes made up by the parser, like the nil
+// of `var x;`, span the tokens they are made from, and have a null span when
+// there are none, like empty blocks.
+use std::collections::HashMap;
+
+use crate::cst::NodeKind;
+use crate::error::Error;
+use crate::json::Json;
+use crate::parser::{self, Expr, NodeSpan, Pattern, Stmt};
+use crate::scanner::{self, Lexeme, LexemeKind};
+use crate::token::{Token, TokenType};
+
+// Version of the JSON dumps, raised whenever their schema changes
+pub const SYNTAX_VERSION: usize = 1;
+
+fn position(offset: usize, line: usize, col: usize) -> Json {
+    Json::object(vec![
+        ("offset", Json::from(offset)),
+        ("line", Json::from(line)),
+        ("col", Json::from(col)),
+    ])
+}
+
+// The span from the start of one lexeme to the end of another
+fn span(first: &Lexeme, last: &Lexeme) -> Json {
+    let text = &last.text;
+    let (line, col) = match text.rfind('\n') {
+        Some(i) => (
+            last.line + text.matches('\n').count(),
+            text[i + 1..].chars().count() + 1,
+        ),
+        None => (last.line, last.col + text.chars().count()),
+    };
+    Json::object(vec![
+        ("start", position(first.offset, first.line, first.col)),
+        ("end", position(last.offset + text.len(), line, col)),
+    ])
+}
+
+// The value of a literal token, None for other tokens
+fn literal(token_type: &TokenType) -> Option<Json> {
+    Some(match token_type {
+        TokenType::Number(n) => Json::from(n.parse::<f64>().unwrap_or(f64::NAN)),
+        TokenType::Text(s) | TokenType::Interpolation(s) => Json::from(s.as_str()),
+        TokenType::True => Json::from(true),
+        TokenType::False => Json::from(false),
+        TokenType::Nil => Json::Null,
+        _ => return None,
+    })
+}
+
+// Text of an operator, as in `a + b` or `!a`
+fn operator(token: &Token) -> &'static str {
+    match token.token_type {
+        TokenType::Minus => "-",
+        TokenType::Plus => "+",
+        TokenType::Slash => "/",
+        TokenType::Star => "*",
+        TokenType::Bang => "!",
+        TokenType::BangEqual => "!=",
+        TokenType::EqualEqual => "==",
+        TokenType::Greater => ">",
+        TokenType::GreaterEqual => ">=",
+        TokenType::Less => "<",
+        TokenType::LessEqual => "<=",
+        TokenType::PlusPlus => "++",
+        TokenType::MinusMinus => "--",
+        TokenType::And => "and",
+        TokenType::Or => "or",
+        _ => "?",
+    }
+}
+
+// Every lexeme of the source but whitespace, as JSON
+pub fn tokens_json(source: &str) -> String {
+    let tokens = scanner::scan_lexemes(source)
+        .iter()
+        .filter_map(|lexeme| {
+            let kind = match &lexeme.kind {
+                LexemeKind::Whitespace => return None,
+                LexemeKind::Comment => "comment",
+                LexemeKind::Token(t) => t.token_type.name(),
+                LexemeKind::Error(_) => "error",
+            };
+            let mut token = vec![
+                ("kind", Json::from(kind)),
+                ("text", Json::from(lexeme.text.as_str())),
+                ("span", span(lexeme, lexeme)),
+            ];
+            match &lexeme.kind {
+                LexemeKind::Token(Token {
+                    token_type:
+                        t @ (TokenType::Number(_) | TokenType::Text(_) | TokenType::Interpolation(_)),
+                    ..
+                }) => token.extend(literal(t).map(|value| ("value", value))),
+                LexemeKind::Error(code) => {
+                    token.push(("message", Json::from(scanner::describe(*code))))
+                }
+                _ => {}
+            }
+            Some(Json::object(token))
+        })
+        .collect();
+    let dump = Json::object(vec![
+        ("version", Json::from(SYNTAX_VERSION)),
+        ("tokens", Json::Array(tokens)),
+    ]);
+    dump.to_string()
+}
+
+// Every lexeme of the source but whitespace, a line each with its line,
+// column, kind and text
+pub fn tokens_text(source: &str) -> String {
+    let mut out = String::new();
+    for lexeme in scanner::scan_lexemes(source) {
+        let kind = match &lexeme.kind {
+            LexemeKind::Whitespace => continue,
+            LexemeKind::Comment => "comment",
+            LexemeKind::Token(t) => t.token_type.name(),
+            LexemeKind::Error(_) => "error",
+        };
+        let text = Json::from(lexeme.text.as_str());
+        out.push_str(&format!(
+            "{}:{} {} {}\n",
+            lexeme.line, lexeme.col, kind, text
+        ));
+    }
+    out
+}
+
+// The statements of the lexemes and the nodes they are parsed from
+fn parse(lexemes: &[Lexeme]) -> Result<(Vec<Stmt>, Vec<NodeSpan>), Error> {
+    let (tokens, mut errors, _) = scanner::split_lexemes(lexemes);
+    let (stmts, parse_errors, spans) = parser::parse_spans(tokens);
+    errors.extend(parse_errors);
+    if !errors.is_empty() {
+        return Err(Error::Syntax(errors));
+    }
+    Ok((stmts, spans))
+}
+
+// The syntax tree of the source as JSON. Only programs without syntax errors
+// are dumped
+pub fn ast_json(source: &str) -> Result<String, Error> {
+    let lexemes = scanner::scan_lexemes(source);
+    let (stmts, spans) = parse(&lexemes)?;
+    let tokens: Vec<&Lexeme> = lexemes
+        .iter()
+        .filter(|lexeme| matches!(lexeme.kind, LexemeKind::Token(_)))
+        .collect();
+    let indices = tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(i, lexeme)| match &lexeme.kind {
+            LexemeKind::Token(t) => Some(((t.line, t.col), i)),
+            _ => None,
+        })
+        .collect();
+    let tree = Tree {
+        tokens,
+        indices,
+        spans,
+    };
+
+    let all = (0, tree.tokens.len());
+    let stmts = stmts.iter().map(|stmt| tree.stmt(stmt, all, None));
+    let dump = Json::object(vec![
+        ("version", Json::from(SYNTAX_VERSION)),
+        ("statements", Json::Array(stmts.collect())),
+    ]);
+    Ok(dump.to_string())
+}
+
+// The tokens of a node, synthesized ones sharing the position of the token they
+// are made from
+#[derive(Default)]
+struct Tokens<'a>(Vec<&'a Token>);
+
+impl<'a> Tokens<'a> {
+    fn stmts(&mut self, stmts: &'a [Stmt]) {
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::Expression(expr) | Stmt::Print(expr) | Stmt::Return(expr) | Stmt::Throw(expr) => {
+                self.expr(expr)
+            }
+            Stmt::Var(name, expr) => {
+                self.0.push(name);
+                self.expr(expr);
+            }
+            Stmt::Function(function) => {
+                self.0.push(&function.name);
+                self.0.extend(&function.params);
+                self.stmts(&function.body);
+            }
+            Stmt::Block(stmts) => self.stmts(stmts),
+            Stmt::If(cond, then, otherwise) => {
+                self.expr(cond);
+                self.stmt(then);
+                if let Some(otherwise) = otherwise.as_ref() {
+                    self.stmt(otherwise);
+                }
+            }
+            Stmt::While(cond, body) => {
+                self.expr(cond);
+                self.stmt(body);
+            }
+            Stmt::Try(stmt_try) => {
+                self.stmts(&stmt_try.body);
+                if let Some((name, body)) = &stmt_try.catch {
+                    self.0.push(name);
+                    self.stmts(body);
+                }
+                if let Some(finally) = &stmt_try.finally {
+                    self.stmts(finally);
+                }
+            }
+            Stmt::Import(path, name) => self.0.extend([path, name]),
+            Stmt::Match(subject, arms) => {
+                self.expr(subject);
+                for arm in arms {
+                    for pattern in &arm.patterns {
+                        match pattern {
+                            Pattern::Literal(expr) => self.expr(expr),
+                            Pattern::Binding(name) => self.0.push(name),
+                            Pattern::Default => {}
+                        }
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard);
+                    }
+                    self.stmt(&arm.body);
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Leaf(t) => self.0.push(t),
+            Expr::Assign(t, expr)
+            | Expr::Unary(t, expr)
+            | Expr::Postfix(t, expr)
+            | Expr::Get(t, expr) => {
+                self.0.push(t);
+                self.expr(expr);
+            }
+            Expr::Binary(t, lhs, rhs) | Expr::Logical(t, lhs, rhs) | Expr::Set(t, lhs, rhs) => {
+                self.0.push(t);
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            Expr::Grouping(expr) | Expr::Stringify(expr) => self.expr(expr),
+            Expr::Call(t, callee, args) => {
+                self.0.push(t);
+                self.expr(callee);
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            Expr::Conditional(cond, then, otherwise) => {
+                self.expr(cond);
+                self.expr(then);
+                self.expr(otherwise);
+            }
+        }
+    }
+}
+
+// Ranges of tokens, from the first up to but without the last
+type Range = (usize, usize);
+
+struct Tree<'a> {
+    // the token lexemes of the source
+    tokens: Vec<&'a Lexeme>,
+    // index of the token at each line and column
+    indices: HashMap<(usize, usize), usize>,
+    spans: Vec<NodeSpan>,
+}
+
+impl Tree<'_> {
+    // The tokens the node was parsed from: the smallest node of one of the kinds
+    // the parser makes it from, that has all of its tokens and lies within its
+    // parent, but isn't the parent. Otherwise just the range of its own tokens
+    fn range(
+        &self,
+        kinds: &[NodeKind],
+        tokens: Tokens,
+        within: Range,
+        parent: Option<Range>,
+    ) -> Option<Range> {
+        let indices = tokens
+            .0
+            .iter()
+            .filter_map(|t| self.indices.get(&(t.line, t.col)));
+        let lo = *indices.clone().min()?;
+        let hi = *indices.max()? + 1;
+        let node = self
+            .spans
+            .iter()
+            .filter(|span| kinds.contains(&span.kind))
+            .map(|span| (span.start, span.end))
+            .filter(|&(start, end)| {
+                start <= lo && hi <= end && within.0 <= start && end <= within.1
+            })
+            .filter(|&range| Some(range) != parent)
+            .min_by_key(|&(start, end)| end - start);
+        Some(node.unwrap_or((lo, hi)))
+    }
+
+    fn node(&self, kind: &str, range: Option<Range>, members: Vec<(&str, Json)>) -> Json {
+        let span = range.map_or(Json::Null, |(start, end)| {
+            span(self.tokens[start], self.tokens[end - 1])
+        });
+        let mut node = vec![("type", Json::from(kind)), ("span", span)];
+        node.extend(members);
+        Json::object(node)
+    }
+
+    fn stmts(&self, stmts: &[Stmt], within: Range, parent: Option<Range>) -> Json {
+        Json::Array(
+            stmts
+                .iter()
+                .map(|stmt| self.stmt(stmt, within, parent))
+                .collect(),
+        )
+    }
+
+    fn stmt(&self, stmt: &Stmt, within: Range, parent: Option<Range>) -> Json {
+        let kinds: &[NodeKind] = match stmt {
+            Stmt::Expression(_) => &[NodeKind::ExprStmt],
+            Stmt::Function(_) => &[NodeKind::Function],
+            Stmt::Return(_) => &[NodeKind::Return],
+            Stmt::Print(_) => &[NodeKind::Print],
+            Stmt::Var(..) => &[NodeKind::Var],
+            Stmt::Block(_) => &[NodeKind::Block, NodeKind::For],
+            Stmt::If(..) => &[NodeKind::If],
+            Stmt::While(..) => &[NodeKind::While, NodeKind::For],
+            Stmt::Throw(_) => &[NodeKind::Throw],
+            Stmt::Try(_) => &[NodeKind::Try],
+            Stmt::Import(..) => &[NodeKind::Import],
+            Stmt::Match(..) => &[NodeKind::Match],
+        };
+        let mut tokens = Tokens::default();
+        tokens.stmt(stmt);
+        let range = self.range(kinds, tokens, within, parent);
+        // children lie within the node, or its parent when it has no tokens
+        let (within, parent) = match range {
+            Some(range) => (range, Some(range)),
+            None => (within, parent),
+        };
+        let expr = |expr| self.expr(expr, within, parent);
+        let stmts = |stmts| self.stmts(stmts, within, parent);
+
+        let (kind, members) = match stmt {
+            Stmt::Expression(e) => ("Expression", vec![("expression", expr(e))]),
+            Stmt::Print(e) => ("Print", vec![("expression", expr(e))]),
+            Stmt::Return(e) => ("Return", vec![("value", expr(e))]),
+            Stmt::Throw(e) => ("Throw", vec![("value", expr(e))]),
+            Stmt::Var(name, e) => (
+                "Var",
+                vec![("name", Json::from(name.name())), ("initializer", expr(e))],
+            ),
+            Stmt::Function(function) => {
+                let params = function.params.iter().map(|p| Json::from(p.name()));
+                (
+                    "Function",
+                    vec![
+                        ("name", Json::from(function.name.name())),
+                        ("params", Json::Array(params.collect())),
+                        ("body", stmts(&function.body)),
+                    ],
+                )
+            }
+            Stmt::Block(body) => ("Block", vec![("statements", stmts(body))]),
+            Stmt::If(cond, then, otherwise) => {
+                let otherwise = otherwise.as_ref().as_ref();
+                let otherwise = otherwise.map_or(Json::Null, |s| self.stmt(s, within, parent));
+                (
+                    "If",
+                    vec![
+                        ("condition", expr(cond)),
+                        ("then", self.stmt(then, within, parent)),
+                        ("else", otherwise),
+                    ],
+                )
+            }
+            Stmt::While(cond, body) => (
+                "While",
+                vec![
+                    ("condition", expr(cond)),
+                    ("body", self.stmt(body, within, parent)),
+                ],
+            ),
+            Stmt::Try(stmt_try) => {
+                let catch = stmt_try.catch.as_ref().map_or(Json::Null, |(name, body)| {
+                    Json::object(vec![
+                        ("name", Json::from(name.name())),
+                        ("body", stmts(body)),
+                    ])
+                });
+                let finally = stmt_try.finally.as_ref();
+                (
+                    "Try",
+                    vec![
+                        ("body", stmts(&stmt_try.body)),
+                        ("catch", catch),
+                        ("finally", finally.map_or(Json::Null, |body| stmts(body))),
+                    ],
+                )
+            }
+            Stmt::Import(path, name) => {
+                let path = literal(&path.token_type).unwrap_or(Json::Null);
+                (
+                    "Import",
+                    vec![("path", path), ("name", Json::from(name.name()))],
+                )
+            }
+            Stmt::Match(subject, arms) => {
+                let arms = arms.iter().map(|arm| {
+                    let patterns = arm.patterns.iter().map(|pattern| match pattern {
+                        Pattern::Literal(e) => Json::object(vec![
+                            ("type", Json::from("ValuePattern")),
+                            ("value", expr(e)),
+                        ]),
+                        Pattern::Binding(name) => Json::object(vec![
+                            ("type", Json::from("BindingPattern")),
+                            ("name", Json::from(name.name())),
+                        ]),
+                        Pattern::Default => {
+                            Json::object(vec![("type", Json::from("DefaultPattern"))])
+                        }
+                    });
+                    Json::object(vec![
+                        ("patterns", Json::Array(patterns.collect())),
+                        ("guard", arm.guard.as_ref().map_or(Json::Null, expr)),
+                        ("body", self.stmt(&arm.body, within, parent)),
+                    ])
+                });
+                (
+                    "Match",
+                    vec![
+                        ("subject", expr(subject)),
+                        ("arms", Json::Array(arms.collect())),
+                    ],
+                )
+            }
+        };
+        self.node(kind, range, members)
+    }
+
+    fn expr(&self, expr: &Expr, within: Range, parent: Option<Range>) -> Json {
+        let kinds: &[NodeKind] = match expr {
+            Expr::Leaf(_) => &[NodeKind::Literal, NodeKind::Variable],
+            // `++a` is an assignment
+            Expr::Assign(..) => &[NodeKind::Assign, NodeKind::Unary],
+            Expr::Unary(..) => &[NodeKind::Unary],
+            Expr::Binary(..) => &[NodeKind::Binary, NodeKind::Interpolation],
+            Expr::Logical(..) => &[NodeKind::Logical],
+            Expr::Grouping(_) => &[NodeKind::Grouping],
+            Expr::Call(..) => &[NodeKind::Call],
+            Expr::Stringify(_) => &[],
+            Expr::Conditional(..) => &[NodeKind::Conditional],
+            Expr::Postfix(..) => &[NodeKind::Postfix],
+            Expr::Get(..) => &[NodeKind::Get],
+            Expr::Set(..) => &[NodeKind::Assign],
+        };
+        let mut tokens = Tokens::default();
+        tokens.expr(expr);
+        let range = self.range(kinds, tokens, within, parent);
+        let (within, parent) = match range {
+            Some(range) => (range, Some(range)),
+            None => (within, parent),
+        };
+        let sub = |expr| self.expr(expr, within, parent);
+
+        let (kind, members) = match expr {
+            Expr::Leaf(t) => match literal(&t.token_type) {
+                Some(value) => ("Literal", vec![("value", value)]),
+                None => ("Variable", vec![("name", Json::from(t.name()))]),
+            },
+            Expr::Assign(name, value) => (
+                "Assign",
+                vec![("name", Json::from(name.name())), ("value", sub(value))],
+            ),
+            Expr::Unary(op, operand) | Expr::Postfix(op, operand) => (
+                if matches!(expr, Expr::Unary(..)) {
+                    "Unary"
+                } else {
+                    "Postfix"
+                },
+                vec![
+                    ("operator", Json::from(operator(op))),
+                    ("operand", sub(operand)),
+                ],
+            ),
+            Expr::Binary(op, lhs, rhs) | Expr::Logical(op, lhs, rhs) => (
+                if matches!(expr, Expr::Binary(..)) {
+                    "Binary"
+                } else {
+                    "Logical"
+                },
+                vec![
+                    ("operator", Json::from(operator(op))),
+                    ("left", sub(lhs)),
+                    ("right", sub(rhs)),
+                ],
+            ),
+            Expr::Grouping(e) => ("Grouping", vec![("expression", sub(e))]),
+            Expr::Stringify(e) => ("Stringify", vec![("expression", sub(e))]),
+            Expr::Call(_, callee, args) => (
+                "Call",
+                vec![
+                    ("callee", sub(callee)),
+                    ("arguments", Json::Array(args.iter().map(sub).collect())),
+                ],
+            ),
+            Expr::Conditional(cond, then, otherwise) => (
+                "Conditional",
+                vec![
+                    ("condition", sub(cond)),
+                    ("then", sub(then)),
+                    ("else", sub(otherwise)),
+                ],
+            ),
+            Expr::Get(name, object) => (
+                "Get",
+                vec![("object", sub(object)), ("name", Json::from(name.name()))],
+            ),
+            Expr::Set(name, object, value) => (
+                "Set",
+                vec![
+                    ("object", sub(object)),
+                    ("name", Json::from(name.name())),
+                    ("value", sub(value)),
+                ],
+            ),
+        };
+        self.node(kind, range, members)
+    }
+}
+
+// The syntax tree of the source as S-expressions, a line per statement, like
+// `(print (+ 1 (* 2 x)))`. Only programs without syntax errors are dumped
+pub fn ast_sexp(source: &str) -> Result<String, Error> {
+    let (stmts, _) = parse(&scanner::scan_lexemes(source))?;
+    let mut out = String::new();
+    for stmt in &stmts {
+        sexp_stmt(stmt, &mut out);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn sexp_list(head: &str, out: &mut String, items: impl FnOnce(&mut String)) {
+    out.push('(');
+    out.push_str(head);
+    items(out);
+    out.push(')');
+}
+
+fn sexp_stmts(stmts: &[Stmt], out: &mut String) {
+    for stmt in stmts {
+        out.push(' ');
+        sexp_stmt(stmt, out);
+    }
+}
+
+fn sexp_stmt(stmt: &Stmt, out: &mut String) {
+    let sub = |expr, out: &mut String| {
+        out.push(' ');
+        sexp_expr(expr, out);
+    };
+    match stmt {
+        Stmt::Expression(e) => sexp_list("expr", out, |out| sub(e, out)),
+        Stmt::Print(e) => sexp_list("print", out, |out| sub(e, out)),
+        Stmt::Return(e) => sexp_list("return", out, |out| sub(e, out)),
+        Stmt::Throw(e) => sexp_list("throw", out, |out| sub(e, out)),
+        Stmt::Var(name, e) => sexp_list("var", out, |out| {
+            out.push_str(&format!(" {}", name.name()));
+            sub(e, out);
+        }),
+        Stmt::Function(function) => sexp_list("fun", out, |out| {
+            let params: Vec<_> = function.params.iter().map(Token::name).collect();
+            out.push_str(&format!(" {} ({})", function.name.name(), params.join(" ")));
+            sexp_stmts(&function.body, out);
+        }),
+        Stmt::Block(stmts) => sexp_list("block", out, |out| sexp_stmts(stmts, out)),
+        Stmt::If(cond, then, otherwise) => sexp_list("if", out, |out| {
+            sub(cond, out);
+            sexp_stmts(std::slice::from_ref(then), out);
+            if let Some(otherwise) = otherwise.as_ref() {
+                sexp_stmts(std::slice::from_ref(otherwise), out);
+            }
+        }),
+        Stmt::While(cond, body) => sexp_list("while", out, |out| {
+            sub(cond, out);
+            sexp_stmts(std::slice::from_ref(body), out);
+        }),
+        Stmt::Try(stmt_try) => sexp_list("try", out, |out| {
+            out.push(' ');
+            sexp_list("block", out, |out| sexp_stmts(&stmt_try.body, out));
+            if let Some((name, body)) = &stmt_try.catch {
+                out.push(' ');
+                sexp_list("catch", out, |out| {
+                    out.push_str(&format!(" {}", name.name()));
+                    sexp_stmts(body, out);
+                });
+            }
+            if let Some(finally) = &stmt_try.finally {
+                out.push(' ');
+                sexp_list("finally", out, |out| sexp_stmts(finally, out));
+            }
+        }),
+        Stmt::Import(path, name) => sexp_list("import", out, |out| {
+            let path = literal(&path.token_type).unwrap_or(Json::Null);
+            out.push_str(&format!(" {} {}", path, name.name()));
+        }),
+        Stmt::Match(subject, arms) => sexp_list("match", out, |out| {
+            sub(subject, out);
+            for arm in arms {
+                out.push(' ');
+                sexp_list("case", out, |out| {
+                    out.push_str(" (");
+                    for (i, pattern) in arm.patterns.iter().enumerate() {
+                        if i > 0 {
+                            out.push(' ');
+                        }
+                        match pattern {
+                            Pattern::Literal(e) => sexp_expr(e, out),
+                            Pattern::Binding(name) => out.push_str(name.name()),
+                            Pattern::Default => out.push_str("default"),
+                        }
+                    }
+                    out.push(')');
+                    if let Some(guard) = &arm.guard {
+                        out.push(' ');
+                        sexp_list("if", out, |out| sub(guard, out));
+                    }
+                    sexp_stmts(std::slice::from_ref(&arm.body), out);
+                });
+            }
+        }),
+    }
+}
+
+fn sexp_expr(expr: &Expr, out: &mut String) {
+    let sub = |expr, out: &mut String| {
+        out.push(' ');
+        sexp_expr(expr, out);
+    };
+    match expr {
+        Expr::Leaf(t) => match &t.token_type {
+            TokenType::Number(n) | TokenType::Identifier(n) => out.push_str(n),
+            TokenType::Text(s) => out.push_str(&Json::from(s.as_str()).to_string()),
+            token_type => out.push_str(token_type.name()),
+        },
+        Expr::Assign(name, value) => sexp_list("=", out, |out| {
+            out.push_str(&format!(" {}", name.name()));
+            sub(value, out);
+        }),
+        Expr::Unary(op, operand) => sexp_list(operator(op), out, |out| sub(operand, out)),
+        Expr::Postfix(op, operand) => sexp_list(&format!("postfix{}", operator(op)), out, |out| {
+            sub(operand, out)
+        }),
+        Expr::Binary(op, lhs, rhs) | Expr::Logical(op, lhs, rhs) => {
+            sexp_list(operator(op), out, |out| {
+                sub(lhs, out);
+                sub(rhs, out);
+            })
+        }
+        Expr::Grouping(e) => sexp_list("group", out, |out| sub(e, out)),
+        Expr::Stringify(e) => sexp_list("str", out, |out| sub(e, out)),
+        Expr::Call(_, callee, args) => sexp_list("call", out, |out| {
+            sub(callee, out);
+            for arg in args {
+                sub(arg, out);
+            }
+        }),
+        Expr::Conditional(cond, then, otherwise) => sexp_list("?", out, |out| {
+            sub(cond, out);
+            sub(then, out);
+            sub(otherwise, out);
+        }),
+        Expr::Get(name, object) => sexp_list(".", out, |out| {
+            sub(object, out);
+            out.push_str(&format!(" {}", name.name()));
+        }),
+        Expr::Set(name, object, value) => sexp_list(".=", out, |out| {
+            sub(object, out);
+            out.push_str(&format!(" {}", name.name()));
+            sub(value, out);
+        }),
+    }
+}
@@ -34,8 +34,23 @@ impl<T: Clone + PartialEq + Default + std::fmt::Debug> EnvStack<T> {
         self.envs.push_back(Default::default())
     }
 
-    pub fn push(&mut self, env: Environment<T>) {
-        self.envs.push_back(env)
+    // Remove every scope but the global one, e.g. to run a function body on top of the globals
+    pub fn take_locals(&mut self) -> VecDeque<Environment<T>> {
+        let globals = self.envs.len().min(1);
+        self.envs.split_off(globals)
+    }
+
+    pub fn restore_locals(&mut self, locals: VecDeque<Environment<T>>) {
+        self.envs.extend(locals)
+    }
+
+    // Swap out the global scope, returning the previous one
+    pub fn replace_globals(&mut self, globals: Environment<T>) -> Environment<T> {
+        std::mem::replace(&mut self.envs[0], globals)
+    }
+
+    pub fn globals(&self) -> Option<&Environment<T>> {
+        self.envs.front()
     }
 
     pub fn pop(&mut self) -> Result<(), Er> {
@@ -69,13 +84,13 @@ impl<T: Clone + PartialEq + Default + std::fmt::Debug> EnvStack<T> {
         Err(Er::Code(47))
     }
 
+    // The scopes, globals first
+    pub fn scopes(&self) -> impl DoubleEndedIterator<Item = &Environment<T>> {
+        self.envs.iter()
+    }
+
     pub fn resolve_depth(&self, name: &str) -> Option<usize> {
-        for i in (0..self.envs.len()).rev() {
-            if self.envs[i].get(name).is_ok() {
-                return Some(i);
-            }
-        }
-        None
+        (0..self.envs.len()).rev().find(|&i| self.envs[i].get(name).is_ok())
     }
 }
 
@@ -90,7 +105,7 @@ impl<T: std::fmt::Debug + Clone + Default> Environment<T> {
     }
 
     pub fn assign(&mut self, name: &str, value: T) -> Result<(), Er> {
-        if self.values.get(name).is_some() {
+        if self.values.contains_key(name) {
             self.values.insert(name.to_string(), value);
             Ok(())
         } else {
@@ -101,4 +116,11 @@ impl<T: std::fmt::Debug + Clone + Default> Environment<T> {
     pub fn get(&self, name: &str) -> Result<&T, usize> {
         self.values.get(name).ok_or(34)
     }
+
+    // The names and values, sorted by name
+    pub fn values(&self) -> Vec<(&String, &T)> {
+        let mut values: Vec<_> = self.values.iter().collect();
+        values.sort_by_key(|(name, _)| *name);
+        values
+    }
 }
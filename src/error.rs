@@ -0,0 +1,53 @@
+use std::fmt;
+
+use crate::limits::Limit;
+
+// Problem reported by lorx, at the line of the source it was found in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Error: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    // The source didn't scan, parse or resolve, nothing of it has run
+    Syntax(Vec<Diagnostic>),
+    // An error nothing caught, the statements before it have run
+    Runtime(Diagnostic),
+    // The run was stopped for going over one of the session's limits
+    ResourceLimit { limit: Limit, line: usize },
+    // The debugger ended the run before the statement at line
+    Stopped { line: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(diagnostics) => {
+                for (i, diagnostic) in diagnostics.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", diagnostic)?;
+                }
+                Ok(())
+            }
+            Error::Runtime(diagnostic) => write!(f, "{}", diagnostic),
+            Error::ResourceLimit { limit, line } => write!(
+                f,
+                "[line {}] Error: Resource limit exceeded, used {}.",
+                line, limit
+            ),
+            Error::Stopped { line } => write!(f, "[line {}] Stopped by the debugger.", line),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,352 @@
+use crate::cst::{Node, SyntaxTree};
+use crate::error::Error;
+use crate::scanner::LexemeKind;
+use crate::token::{keyword_to_token_type, TokenType};
+
+const INDENT: &str = "  ";
+// Lines longer than this are wrapped at the commas of their outermost call or
+// parameter list, if they have one
+const MAX_WIDTH: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Word,
+    Number,
+    // a whole string literal, embedded expressions included, kept as written
+    Text,
+    Punct,
+    Comment,
+}
+
+// A piece of the source as written, with the number of line breaks before it
+#[derive(Debug)]
+struct Lexeme<'a> {
+    kind: Kind,
+    text: &'a str,
+    newlines: usize,
+}
+
+impl Lexeme<'_> {
+    fn is(&self, text: &str) -> bool {
+        self.kind == Kind::Punct && self.text == text
+    }
+
+    fn is_keyword(&self) -> bool {
+        self.kind == Kind::Word && keyword_to_token_type(self.text).is_some()
+    }
+
+    // Whether an operator after it is binary, as in `a - b` but not `(-b)`
+    fn ends_operand(&self) -> bool {
+        match self.kind {
+            Kind::Number | Kind::Text => true,
+            Kind::Word => {
+                !self.is_keyword()
+                    || matches!(self.text, "this" | "super" | "true" | "false" | "nil")
+            }
+            Kind::Punct => self.text == ")",
+            Kind::Comment => false,
+        }
+    }
+}
+
+// The tokens and comments of source that scanned and parsed fine, with the
+// parts of interpolated strings joined, those are kept as written
+fn lex<'a>(source: &'a str, root: &Node) -> Vec<Lexeme<'a>> {
+    let mut lexemes = vec![];
+    let mut newlines = 0;
+    // offset of the interpolated string being joined, and how deeply nested we are in it
+    let mut string = 0;
+    let mut depth = 0;
+    for lexeme in root.lexemes() {
+        let text = &source[lexeme.offset..lexeme.offset + lexeme.text.len()];
+        let token_type = match &lexeme.kind {
+            LexemeKind::Token(token) => &token.token_type,
+            LexemeKind::Whitespace if depth == 0 => {
+                newlines += text.matches('\n').count();
+                continue;
+            }
+            LexemeKind::Comment if depth == 0 => {
+                lexemes.push(Lexeme {
+                    kind: Kind::Comment,
+                    text: text.trim_end(),
+                    newlines,
+                });
+                newlines = 0;
+                continue;
+            }
+            _ => continue,
+        };
+
+        let kind = match token_type {
+            TokenType::Interpolation(_) if !text.starts_with('}') => {
+                if depth == 0 {
+                    string = lexeme.offset;
+                }
+                depth += 1;
+                continue;
+            }
+            TokenType::Interpolation(_) => continue,
+            TokenType::Text(_) if text.starts_with('}') => {
+                depth -= 1;
+                if depth > 0 {
+                    continue;
+                }
+                Kind::Text
+            }
+            _ if depth > 0 => continue,
+            TokenType::Text(_) => Kind::Text,
+            TokenType::Number(_) => Kind::Number,
+            TokenType::Identifier(_) => Kind::Word,
+            _ if keyword_to_token_type(text).is_some() => Kind::Word,
+            _ => Kind::Punct,
+        };
+        let text = if kind == Kind::Text && text.starts_with('}') {
+            &source[string..lexeme.offset + text.len()]
+        } else {
+            text
+        };
+        lexemes.push(Lexeme {
+            kind,
+            text,
+            newlines,
+        });
+        newlines = 0;
+    }
+    lexemes
+}
+
+#[derive(Debug, Default)]
+struct Line<'a> {
+    indent: usize,
+    // tokens with whether a space goes before them
+    atoms: Vec<(&'a str, bool)>,
+    comment: Option<&'a str>,
+    blank_before: bool,
+}
+
+#[derive(Default)]
+struct Formatter<'a> {
+    lines: Vec<Line<'a>>,
+    line: Line<'a>,
+    depth: usize,
+    parens: usize,
+    // the statement so far continues on the next line
+    continued: bool,
+    // a statement or block ended, the next token starts a line
+    break_after: bool,
+    prev: Option<&'a Lexeme<'a>>,
+    // the previous token is a prefix operator, taking no space after it
+    prev_prefix: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn end_line(&mut self) {
+        if self.line.atoms.is_empty() && self.line.comment.is_none() {
+            return;
+        }
+        self.continued |= !self.break_after && !self.line.atoms.is_empty();
+        let line = std::mem::take(&mut self.line);
+        self.lines.push(line);
+    }
+
+    fn start_line(&mut self, lexeme: &Lexeme) {
+        self.line.indent = self.depth + if self.continued { 1 } else { 0 };
+        // no blank lines at the start of the file or of a block
+        let after_open = self
+            .lines
+            .last()
+            .is_none_or(|line| line.atoms.last().is_some_and(|(text, _)| *text == "{"));
+        self.line.blank_before = lexeme.newlines > 1 && !after_open && !lexeme.is("}");
+    }
+
+    fn comment(&mut self, lexeme: &'a Lexeme<'a>) {
+        if lexeme.newlines == 0 && !self.line.atoms.is_empty() {
+            self.line.comment = Some(lexeme.text);
+            self.end_line();
+            return;
+        }
+        self.end_line();
+        if self.break_after {
+            self.continued = false;
+        }
+        self.start_line(lexeme);
+        self.line.comment = Some(lexeme.text);
+        self.lines.push(std::mem::take(&mut self.line));
+    }
+
+    fn token(&mut self, lexeme: &'a Lexeme<'a>) {
+        let prev = self.prev;
+        let prev_is = |text| prev.is_some_and(|prev| prev.is(text));
+        let closes = lexeme.is("}");
+        if closes {
+            self.depth = self.depth.saturating_sub(1);
+        }
+
+        let joins_close = prev_is("}")
+            && matches!(lexeme.text, "else" | "catch" | "finally")
+            && lexeme.kind == Kind::Word;
+        let empty_block = closes && prev_is("{");
+        let breaks = (self.break_after || closes) && !joins_close && !empty_block;
+        if breaks || self.line.atoms.is_empty() {
+            self.end_line();
+            if self.break_after {
+                self.continued = false;
+            }
+            if self.line.atoms.is_empty() {
+                self.start_line(lexeme);
+            }
+        }
+
+        let space = !self.line.atoms.is_empty() && self.space_before(lexeme);
+        self.line.atoms.push((lexeme.text, space));
+
+        // postfix `++` and `--` end an operand too
+        let operand = prev.is_some_and(|prev| prev.ends_operand())
+            || (prev_is("++") || prev_is("--")) && !self.prev_prefix;
+        self.prev_prefix = matches!(lexeme.text, "-" | "!" | "++" | "--")
+            && lexeme.kind == Kind::Punct
+            && !operand;
+
+        match lexeme.text {
+            "{" if lexeme.kind == Kind::Punct => self.depth += 1,
+            "(" if lexeme.kind == Kind::Punct => self.parens += 1,
+            ")" if lexeme.kind == Kind::Punct => self.parens = self.parens.saturating_sub(1),
+            _ => {}
+        }
+        self.break_after = lexeme.kind == Kind::Punct
+            && match lexeme.text {
+                "{" | "}" => true,
+                ";" => self.parens == 0,
+                _ => false,
+            };
+        self.prev = Some(lexeme);
+    }
+
+    fn space_before(&self, lexeme: &Lexeme) -> bool {
+        let prev = match self.prev {
+            Some(prev) => prev,
+            None => return false,
+        };
+        if prev.is("(") || prev.is(".") || self.prev_prefix {
+            return false;
+        }
+        if lexeme.kind != Kind::Punct {
+            return true;
+        }
+        match lexeme.text {
+            ")" | "," | ";" | "." => false,
+            // calls and declarations, but `if (` and `print (`
+            "(" => {
+                let callee = prev.kind == Kind::Word
+                    && (!prev.is_keyword() || matches!(prev.text, "this" | "super"));
+                !(callee || prev.is(")"))
+            }
+            // postfix
+            "++" | "--" => !prev.ends_operand(),
+            "}" => !prev.is("{"),
+            _ => true,
+        }
+    }
+}
+
+fn render_atoms(atoms: &[(&str, bool)]) -> String {
+    let mut text = String::new();
+    for (atom, space) in atoms {
+        if *space {
+            text.push(' ');
+        }
+        text.push_str(atom);
+    }
+    text
+}
+
+// The outermost parenthesized list with more than one element, as the index
+// of its opening and closing parenthesis and of the commas between
+fn outer_list(atoms: &[(&str, bool)]) -> Option<(usize, usize, Vec<usize>)> {
+    let mut depth = 0;
+    let mut open = None;
+    let mut commas = vec![];
+    for (i, (atom, _)) in atoms.iter().enumerate() {
+        match *atom {
+            "(" => {
+                if depth == 0 {
+                    open = Some(i);
+                    commas.clear();
+                }
+                depth += 1;
+            }
+            ")" => {
+                depth -= 1;
+                if depth == 0 && !commas.is_empty() {
+                    return Some((open?, i, commas));
+                }
+            }
+            "," if depth == 1 => commas.push(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn render_line(out: &mut String, indent: usize, atoms: &[(&str, bool)], comment: Option<&str>) {
+    let mut text = INDENT.repeat(indent) + &render_atoms(atoms);
+    // trailing comments may run over
+    let too_long = text.lines().last().map_or(0, |line| line.chars().count()) > MAX_WIDTH;
+    if let Some(comment) = comment {
+        if !atoms.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(comment);
+    }
+
+    if let (true, Some((open, close, commas))) = (too_long, outer_list(atoms)) {
+        render_line(out, indent, &atoms[..=open], None);
+        let mut start = open + 1;
+        for end in commas.into_iter().chain(Some(close)) {
+            let mut element = atoms[start..end].to_vec();
+            if end != close {
+                element.push((",", false));
+            }
+            if let Some(first) = element.first_mut() {
+                first.1 = false;
+            }
+            render_line(out, indent + 1, &element, None);
+            start = end + 1;
+        }
+        let mut rest = atoms[close..].to_vec();
+        rest[0].1 = false;
+        render_line(out, indent, &rest, comment);
+        return;
+    }
+    out.push_str(&text);
+    out.push('\n');
+}
+
+// The source laid out consistently, with its comments kept. Only programs
+// without syntax errors are formatted
+pub fn format_source(source: &str) -> Result<String, Error> {
+    let tree = SyntaxTree::parse(source);
+    if !tree.errors.is_empty() {
+        return Err(Error::Syntax(tree.errors));
+    }
+
+    let lexemes = lex(source, &tree.root);
+    let mut formatter = Formatter::default();
+    for lexeme in &lexemes {
+        if lexeme.kind == Kind::Comment {
+            formatter.comment(lexeme);
+        } else {
+            formatter.token(lexeme);
+        }
+    }
+    formatter.end_line();
+
+    let mut out = String::new();
+    for line in &formatter.lines {
+        if line.blank_before && !out.is_empty() {
+            out.push('\n');
+        }
+        render_line(&mut out, line.indent, &line.atoms, line.comment);
+    }
+    Ok(out)
+}
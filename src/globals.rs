@@ -1,5 +1,11 @@
-use crate::callable::Callable;
+use std::rc::Rc;
+
+use crate::callable::Arity;
 use crate::interpreter::Interpreter;
+use crate::native::{IntoNative, NativeFunction};
+use crate::permissions::Permissions;
+use crate::replay::Sources;
+use crate::stdlib;
 use crate::value::{Er, Value};
 
 pub struct Globals {
@@ -8,27 +14,42 @@ pub struct Globals {
 
 impl Globals {
     pub fn new() -> Self {
-        Globals {
-            functions: vec![
-                ("clock".to_string(), Value::Callable(std::rc::Rc::new(Clock {})))
-            ]
-        }
+        let mut globals = Globals { functions: vec![] };
+        globals.define("clock", |permissions: Permissions, sources: Sources| {
+            permissions.check_clock()?;
+            sources.clock()
+        });
+        globals.define(
+            "getEnv",
+            |permissions: Permissions, sources: Sources, name: String| {
+                permissions.check_env()?;
+                sources.env(&name)
+            },
+        );
+        stdlib::string::register(&mut globals);
+        stdlib::math::register(&mut globals);
+        stdlib::io::register(&mut globals);
+        stdlib::types::register(&mut globals);
+        stdlib::collections::register(&mut globals);
+        globals
     }
-}
 
-#[derive(Debug)]
-pub struct Clock {}
+    pub fn define<Params>(&mut self, name: &str, function: impl IntoNative<Params>) {
+        let native = NativeFunction::new(name, function);
+        self.functions
+            .push((name.to_owned(), Value::Callable(Rc::new(native))));
+    }
 
-impl Callable for Clock {
-    fn artiy(&self) -> usize {
-        0
+    pub fn define_raw<F>(&mut self, name: &str, arity: Arity, function: F)
+    where
+        F: Fn(&mut Interpreter, Vec<Value>) -> Result<Value, Er> + 'static,
+    {
+        let native = NativeFunction::raw(name, arity, function);
+        self.functions
+            .push((name.to_owned(), Value::Callable(Rc::new(native))));
     }
-    fn call(&self, _: &mut Interpreter, _: Vec<Value>) -> Result<Value, Er> {
-        Ok(Value::Number(
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .expect("error")
-                .as_millis() as f64,
-        ))
+
+    pub fn constant(&mut self, name: &str, value: Value) {
+        self.functions.push((name.to_owned(), value));
     }
 }
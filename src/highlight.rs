@@ -0,0 +1,128 @@
+use crate::cst::SyntaxTree;
+use crate::scanner::{Lexeme, LexemeKind};
+use crate::token::TokenType;
+
+// Kind of a lexeme for highlighting, also its CSS class. None for whitespace
+fn class(lexeme: &Lexeme) -> Option<&'static str> {
+    let token_type = match &lexeme.kind {
+        LexemeKind::Whitespace => return None,
+        LexemeKind::Comment => return Some("comment"),
+        // text that doesn't scan is kept as is, marked as an error
+        LexemeKind::Error(_) => return Some("error"),
+        LexemeKind::Token(token) => &token.token_type,
+    };
+    Some(match token_type {
+        TokenType::Identifier(_) => "identifier",
+        TokenType::Number(_) => "number",
+        TokenType::Text(_) | TokenType::Interpolation(_) => "string",
+        TokenType::And
+        | TokenType::Class
+        | TokenType::Else
+        | TokenType::False
+        | TokenType::Fun
+        | TokenType::For
+        | TokenType::If
+        | TokenType::Nil
+        | TokenType::Or
+        | TokenType::Print
+        | TokenType::Return
+        | TokenType::Super
+        | TokenType::This
+        | TokenType::True
+        | TokenType::Var
+        | TokenType::While
+        | TokenType::Throw
+        | TokenType::Try
+        | TokenType::Catch
+        | TokenType::Finally
+        | TokenType::Import
+        | TokenType::Match
+        | TokenType::Case
+        | TokenType::Default => "keyword",
+        // punctuation included
+        _ => "operator",
+    })
+}
+
+// SGR parameters of each class, identifiers keep the default colour
+fn ansi_style(class: &str) -> Option<&'static str> {
+    match class {
+        "keyword" => Some("35"),
+        "number" => Some("33"),
+        "string" => Some("32"),
+        "comment" => Some("90"),
+        "operator" => Some("36"),
+        "error" => Some("4;31"),
+        _ => None,
+    }
+}
+
+// The source with ANSI colours for terminals. Every line is styled on its own,
+// so the result may be split into lines, e.g. to show a few of them
+pub fn highlight_ansi(source: &str) -> String {
+    let mut out = String::new();
+    for lexeme in SyntaxTree::parse(source).root.lexemes() {
+        let style = class(lexeme).and_then(ansi_style);
+        let style = match style {
+            Some(style) => style,
+            None => {
+                out.push_str(&lexeme.text);
+                continue;
+            }
+        };
+        for (i, line) in lexeme.text.split('\n').enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            if !line.is_empty() {
+                out.push_str(&format!("\x1b[{}m{}\x1b[0m", style, line));
+            }
+        }
+    }
+    out
+}
+
+const STYLE: &str = "pre.lox { background: #fafafa; color: #383a42; padding: 1em; }
+.lox .keyword { color: #a626a4; }
+.lox .identifier { color: #383a42; }
+.lox .number { color: #986801; }
+.lox .string { color: #50a14f; }
+.lox .comment { color: #a0a1a7; font-style: italic; }
+.lox .operator { color: #0184bc; }
+.lox .error { color: #e45649; text-decoration: wavy underline; }";
+
+fn escape_html(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+// The source as a standalone HTML page, every lexeme but whitespace in a span
+// with the class of its kind: keyword, identifier, number, string, comment,
+// operator or error
+pub fn highlight_html(source: &str, title: &str) -> String {
+    let mut out = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>");
+    escape_html(title, &mut out);
+    out.push_str("</title>\n<style>\n");
+    out.push_str(STYLE);
+    out.push_str("\n</style>\n</head>\n<body>\n<pre class=\"lox\"><code>");
+    for lexeme in SyntaxTree::parse(source).root.lexemes() {
+        match class(lexeme) {
+            Some(class) => {
+                out.push_str(&format!("<span class=\"{}\">", class));
+                escape_html(&lexeme.text, &mut out);
+                out.push_str("</span>");
+            }
+            None => escape_html(&lexeme.text, &mut out),
+        }
+    }
+    out.push_str("</code></pre>\n</body>\n</html>\n");
+    out
+}
@@ -0,0 +1,34 @@
+use std::any::Any;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::native::NativeFunction;
+use crate::value::Value;
+
+// Rust object handed to Lox, which scripts use like an instance of a Lox class.
+// Properties shadow methods of the same name, like fields do in Lox
+pub trait HostObject: Any + fmt::Debug {
+    // Name of the object's class, printed as in `Config instance`
+    fn class_name(&self) -> &str;
+
+    // Value of a property, None if the object has no such property
+    fn get(&self, _name: &str) -> Option<Value> {
+        None
+    }
+
+    // Assign a property, the Err message is raised as a runtime error. Objects are
+    // shared between Lox and Rust, so mutable ones need interior mutability
+    fn set(&self, name: &str, _value: Value) -> Result<(), String> {
+        Err(format!(
+            "Can't set property '{}' on {} instance.",
+            name,
+            self.class_name()
+        ))
+    }
+
+    // Method bound to this object, None if there is no such method. Built with
+    // NativeFunction::new, it gets its arguments checked like any native
+    fn method(self: Rc<Self>, _name: &str) -> Option<NativeFunction> {
+        None
+    }
+}
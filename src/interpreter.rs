@@ -1,10 +1,22 @@
 use crate::callable::Function;
-use crate::environment::EnvStack;
-use crate::parser::{Expr, Stmt};
+use crate::cst::SyntaxTree;
+use crate::debug::{Debugging, Frame};
+use crate::environment::{EnvStack, Environment};
+use crate::globals::Globals;
+use crate::limits::Budget;
+use crate::permissions::Permissions;
+use crate::replay::Sources;
+use crate::resolver::Resolver;
+use crate::parser::{Expr, MatchArm, Pattern, Stmt, StmtTry};
 use crate::token::{Token, TokenType};
-use crate::value::{Er, Value};
+use crate::stdlib::math::Random;
+use crate::streams::Streams;
+use crate::value::{describe, Er, LoxError, Module, Value};
 
 use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 fn cast_to_num(v: &Value) -> Result<f64, Er> {
     if let Value::Number(n) = v {
@@ -14,6 +26,14 @@ fn cast_to_num(v: &Value) -> Result<f64, Er> {
     }
 }
 
+// Both operands of a binary operator as numbers
+fn cast_operands(lhs: &Value, rhs: &Value) -> Result<(f64, f64), Er> {
+    match (lhs, rhs) {
+        (Value::Number(lhs), Value::Number(rhs)) => Ok((*lhs, *rhs)),
+        _ => Err(Er::Code(11)),
+    }
+}
+
 fn cast_to_string(v: &Value) -> Result<&String, Er> {
     if let Value::Text(s) = v {
         Ok(s)
@@ -22,13 +42,16 @@ fn cast_to_string(v: &Value) -> Result<&String, Er> {
     }
 }
 
-fn is_truthy(v: &Value) -> bool {
+pub fn is_truthy(v: &Value) -> bool {
     match v {
         Value::Text(_) => true,
         Value::Number(_) => true,
         Value::Boolean(b) => *b,
         Value::Nil => false,
         Value::Callable(_) => true,
+        Value::Error(_) => true,
+        Value::Module(_) => true,
+        Value::Host(_) => true,
     }
 }
 
@@ -43,35 +66,176 @@ fn is_equal(lhs: &Value, rhs: &Value) -> bool {
 pub struct Interpreter {
     pub envs: EnvStack<Value>,
     pub locals: HashMap<Expr, usize>,
+    // line of the innermost expression evaluated so far, attached to runtime errors
+    pub line: usize,
+    // file whose globals are the bottom scope of envs
+    pub module: PathBuf,
+    // global scopes of the other loaded files, keyed by canonical path
+    pub modules: HashMap<PathBuf, Environment<Value>>,
+    // files whose top level is currently running, to detect circular imports
+    pub importing: Vec<PathBuf>,
+    // directory of the file the session runs, None for sessions running
+    // snippets. Scripts may import the files below it without read access
+    pub root: Option<PathBuf>,
+    // generator behind random() and randomInt(), shared by all modules
+    pub random: Random,
+    pub streams: Streams,
+    pub budget: Budget,
+    pub permissions: Permissions,
+    // where clock(), random(), readLine() and getEnv() read from
+    pub sources: Sources,
+    pub debugging: Debugging,
 }
 
 impl Interpreter {
+    // Raise a runtime error at the current line, catchable from Lox
+    pub fn error(&self, message: String) -> Er {
+        Er::Throw(Value::Error(Rc::new(LoxError {
+            message,
+            line: self.line,
+        })))
+    }
+
+    // Turn an error into the value a catch clause binds, returns aren't exceptions
+    fn exception(&self, er: Er) -> Result<Value, Er> {
+        match er {
+            Er::Throw(value) => Ok(value),
+            Er::Code(code) => Ok(Value::Error(Rc::new(LoxError {
+                message: describe(code).to_owned(),
+                line: self.line,
+            }))),
+            Er::Return(_) | Er::Limit(_) | Er::Stopped | Er::Diverged(_) => Err(er),
+        }
+    }
+
+    // Make the globals of the given file the active ones, returning the previously active file
+    pub fn enter_module(&mut self, module: &Path) -> Result<PathBuf, Er> {
+        let previous = self.module.clone();
+        if previous != module {
+            let globals = self.modules.remove(module).ok_or(Er::Code(48))?;
+            let outer = self.envs.replace_globals(globals);
+            self.modules.insert(previous.clone(), outer);
+            self.module = module.to_owned();
+        }
+        Ok(previous)
+    }
+
+    fn eval_import(&mut self, token: &Token, name: &str) -> Result<(), Er> {
+        self.line = token.line;
+        let relative = match &token.token_type {
+            TokenType::Text(s) => s,
+            _ => return Err(Er::Code(13)),
+        };
+
+        // paths are relative to the importing file
+        let base = self.module.parent().unwrap_or_else(|| Path::new(""));
+        let path = base.join(relative);
+        if !self.may_import(&path) {
+            return Err(self.error(format!(
+                "Permission denied: no read access to '{}'.",
+                relative
+            )));
+        }
+        let path = path
+            .canonicalize()
+            .map_err(|e| self.error(format!("Could not import '{}': {}.", relative, e)))?;
+
+        if self.importing.contains(&path) {
+            return Err(self.error(format!("Circular import of '{}'.", relative)));
+        }
+
+        // modules are cached, so their top level only runs on the first import
+        if !self.modules.contains_key(&path) {
+            self.load_module(&path, relative)?;
+        }
+
+        let module = Module {
+            name: name.to_owned(),
+            path,
+        };
+        self.envs.define(name, Value::Module(Rc::new(module)));
+        Ok(())
+    }
+
+    // Scripts may import the files below the directory of the file the session
+    // runs, like the ones they ship with, anything else takes read access
+    fn may_import(&self, path: &Path) -> bool {
+        let below_root = match (&self.root, path.canonicalize()) {
+            (Some(root), Ok(path)) => path.starts_with(root),
+            _ => false,
+        };
+        below_root || self.permissions.check_read(&path.to_string_lossy()).is_ok()
+    }
+
+    // Run the top level of a file on fresh globals, which are kept around as its
+    // namespace, errors name it by the path it was imported with
+    fn load_module(&mut self, path: &Path, relative: &str) -> Result<(), Er> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| self.error(format!("Could not import '{}': {}.", relative, e)))?;
+        let tree = SyntaxTree::parse(&text);
+        let mut resolver = Resolver::default();
+        for stmt in tree.statements() {
+            resolver.resolve_stmt(stmt);
+        }
+        if let Some(e) = tree.errors.iter().chain(&resolver.errors).next() {
+            return Err(self.error(format!(
+                "Could not import '{}': line {}: {}",
+                relative, e.line, e.message
+            )));
+        }
+
+        let mut globals = Environment::default();
+        for (name, value) in Globals::new().functions {
+            globals.define(&name, value);
+        }
+
+        let locals = self.envs.take_locals();
+        self.modules.insert(path.to_owned(), globals);
+        let importer = self.enter_module(path)?;
+        self.importing.push(path.to_owned());
+
+        let mut res = Ok(());
+        for stmt in tree.statements() {
+            res = self.evaluate(stmt);
+            if res.is_err() {
+                break;
+            }
+        }
+
+        self.importing.pop();
+        self.enter_module(&importer)?;
+        self.envs.restore_locals(locals);
+
+        // don't cache modules that failed to load
+        if res.is_err() {
+            self.modules.remove(path);
+        }
+        res
+    }
+
     fn eval_binary(&mut self, token: &Token, lhs: &Expr, rhs: &Expr) -> Result<Value, Er> {
         let lhs_val = self.eval_expr(lhs)?;
         let rhs_val = self.eval_expr(rhs)?;
+        self.line = token.line;
 
+        let numbers = || cast_operands(&lhs_val, &rhs_val);
         Ok(match token.token_type {
-            TokenType::Minus => Value::Number(cast_to_num(&lhs_val)? - cast_to_num(&rhs_val)?),
-            TokenType::Slash => Value::Number(cast_to_num(&lhs_val)? / cast_to_num(&rhs_val)?),
-            TokenType::Star => Value::Number(cast_to_num(&lhs_val)? * cast_to_num(&rhs_val)?),
+            TokenType::Minus => numbers().map(|(lhs, rhs)| Value::Number(lhs - rhs))?,
+            TokenType::Slash => numbers().map(|(lhs, rhs)| Value::Number(lhs / rhs))?,
+            TokenType::Star => numbers().map(|(lhs, rhs)| Value::Number(lhs * rhs))?,
             TokenType::Plus => {
-                let lhs_num = cast_to_num(&lhs_val);
-                let rhs_num = cast_to_num(&rhs_val);
-
-                if lhs_num.is_ok() && rhs_num.is_ok() {
-                    Value::Number(lhs_num.unwrap() + rhs_num.unwrap())
+                if let Ok((lhs_num, rhs_num)) = numbers() {
+                    Value::Number(lhs_num + rhs_num)
                 } else {
-                    Value::Text(cast_to_string(&lhs_val)?.clone() + cast_to_string(&rhs_val)?)
+                    let text = cast_to_string(&lhs_val)?.clone() + cast_to_string(&rhs_val)?;
+                    self.budget.allocate(text.len())?;
+                    Value::Text(text)
                 }
             }
-            TokenType::Greater => Value::Boolean(cast_to_num(&lhs_val)? > cast_to_num(&rhs_val)?),
-            TokenType::GreaterEqual => {
-                Value::Boolean(cast_to_num(&lhs_val)? >= cast_to_num(&rhs_val)?)
-            }
-            TokenType::Less => Value::Boolean(cast_to_num(&lhs_val)? < cast_to_num(&rhs_val)?),
-            TokenType::LessEqual => {
-                Value::Boolean(cast_to_num(&lhs_val)? <= cast_to_num(&rhs_val)?)
-            }
+            TokenType::Greater => numbers().map(|(lhs, rhs)| Value::Boolean(lhs > rhs))?,
+            TokenType::GreaterEqual => numbers().map(|(lhs, rhs)| Value::Boolean(lhs >= rhs))?,
+            TokenType::Less => numbers().map(|(lhs, rhs)| Value::Boolean(lhs < rhs))?,
+            TokenType::LessEqual => numbers().map(|(lhs, rhs)| Value::Boolean(lhs <= rhs))?,
             TokenType::EqualEqual => Value::Boolean(is_equal(&lhs_val, &rhs_val)),
             TokenType::BangEqual => Value::Boolean(!is_equal(&lhs_val, &rhs_val)),
             _ => {
@@ -105,86 +269,229 @@ impl Interpreter {
     }
 
     fn eval_unary(&mut self, token: &Token, rhs: &Expr) -> Result<Value, Er> {
-        let rhs_val = cast_to_num(&self.eval_expr(rhs)?)?;
+        let rhs_val = self.eval_expr(rhs)?;
+        self.line = token.line;
 
         match token.token_type {
-            TokenType::Minus => Ok(Value::Number(-rhs_val)),
+            TokenType::Bang => Ok(Value::Boolean(!is_truthy(&rhs_val))),
+            TokenType::Minus => Ok(Value::Number(-cast_to_num(&rhs_val)?)),
             _ => Err(Er::Code(14)),
         }
     }
 
+    fn eval_postfix(&mut self, token: &Token, target: &Expr) -> Result<Value, Er> {
+        // evaluate the object of a property only once, it is both read and written
+        let object = match target {
+            Expr::Get(_, object) => Some(self.eval_expr(object)?),
+            _ => None,
+        };
+        let old = match (target, &object) {
+            (Expr::Get(name, _), Some(object)) => self.get_property(object.clone(), name)?,
+            _ => self.eval_expr(target)?,
+        };
+        self.line = token.line;
+        let old = cast_to_num(&old)?;
+        let new = match token.token_type {
+            TokenType::PlusPlus => old + 1.0,
+            TokenType::MinusMinus => old - 1.0,
+            _ => return Err(Er::Code(14)),
+        };
+
+        match (target, object) {
+            (
+                Expr::Leaf(Token {
+                    token_type: TokenType::Identifier(name),
+                    ..
+                }),
+                _,
+            ) => self.assign(name, Value::Number(new))?,
+            (Expr::Get(name, _), Some(object)) => {
+                self.set_property(object, name, Value::Number(new))?
+            }
+            _ => return Err(Er::Code(42)),
+        }
+
+        Ok(Value::Number(old))
+    }
+
+    fn eval_conditional(&mut self, cond: &Expr, lhs: &Expr, rhs: &Expr) -> Result<Value, Er> {
+        if is_truthy(&self.eval_expr(cond)?) {
+            self.eval_expr(lhs)
+        } else {
+            self.eval_expr(rhs)
+        }
+    }
+
     fn eval_assign(&mut self, name: &str, rhs: &Expr) -> Result<Value, Er> {
         let rhs_val = self.eval_expr(rhs)?;
 
-        self.envs.assign(name, rhs_val.clone())?;
+        self.assign(name, rhs_val.clone())?;
 
         Ok(rhs_val)
     }
 
-    fn eval_leaf(&self, token: &Token) -> Result<Value, Er> {
+    fn assign(&mut self, name: &str, value: Value) -> Result<(), Er> {
+        self.envs
+            .assign(name, value)
+            .map_err(|_| self.error(format!("Undefined variable '{}'.", name)))
+    }
+
+    fn eval_leaf(&mut self, token: &Token) -> Result<Value, Er> {
+        self.line = token.line;
         Ok(match &token.token_type {
             TokenType::Text(s) => Value::Text(s.clone()),
             TokenType::Number(n) => Value::Number(n.parse::<f64>().unwrap()), // safe unwrap as scanner checks that the string can be converted to a number
             TokenType::True => Value::Boolean(true),
             TokenType::False => Value::Boolean(false),
             TokenType::Nil => Value::Nil,
-            TokenType::Identifier(s) => self.envs.get(s.as_str())?.clone(),
+            TokenType::Identifier(s) => match self.envs.get(s.as_str()) {
+                Ok(value) => value.clone(),
+                Err(_) => return Err(self.error(format!("Undefined variable '{}'.", s))),
+            },
             _ => {
                 return Err(Er::Code(13));
             }
         })
     }
 
-    fn eval_call(&mut self, callee: &Expr, args: &Vec<Expr>) -> Result<Value, Er> {
-        let call = match self.eval_expr(callee)? {
-            Value::Callable(c) => c,
-            _ => {
-                // return an Error on non-callable types returned from callee
-                return Err(Er::Code(1060));
+    fn eval_get(&mut self, name: &Token, object: &Expr) -> Result<Value, Er> {
+        let object = self.eval_expr(object)?;
+        self.get_property(object, name)
+    }
+
+    fn get_property(&mut self, object: Value, name: &Token) -> Result<Value, Er> {
+        self.line = name.line;
+        let field = match &name.token_type {
+            TokenType::Identifier(s) => s.as_str(),
+            _ => return Err(Er::Code(13)),
+        };
+
+        let undefined = || format!("Undefined property '{}'.", field);
+        match (object, field) {
+            (Value::Error(e), "message") => Ok(Value::Text(e.message.clone())),
+            (Value::Error(e), "line") => Ok(Value::Number(e.line as f64)),
+            (Value::Error(_), _) => Err(self.error(undefined())),
+            (Value::Module(m), _) => {
+                let globals = if m.path == self.module {
+                    self.envs.globals()
+                } else {
+                    self.modules.get(&m.path)
+                };
+                match globals.map(|g| g.get(field)) {
+                    Some(Ok(value)) => Ok(value.clone()),
+                    _ => Err(self.error(undefined())),
+                }
             }
+            (Value::Host(host), _) => match host.get(field) {
+                Some(value) => Ok(value),
+                None => match host.method(field) {
+                    Some(method) => Ok(Value::Callable(Rc::new(method))),
+                    None => Err(self.error(undefined())),
+                },
+            },
+            _ => Err(self.error("Only instances have properties.".to_owned())),
+        }
+    }
+
+    fn eval_set(&mut self, name: &Token, object: &Expr, value: &Expr) -> Result<Value, Er> {
+        let object = self.eval_expr(object)?;
+        let value = self.eval_expr(value)?;
+        self.set_property(object, name, value.clone())?;
+        Ok(value)
+    }
+
+    fn set_property(&mut self, object: Value, name: &Token, value: Value) -> Result<(), Er> {
+        self.line = name.line;
+        let field = match &name.token_type {
+            TokenType::Identifier(s) => s.as_str(),
+            _ => return Err(Er::Code(13)),
         };
 
+        match object {
+            Value::Host(host) => host.set(field, value).map_err(|e| self.error(e)),
+            _ => Err(self.error("Only instances have fields.".to_owned())),
+        }
+    }
+
+    fn eval_call(&mut self, token: &Token, callee: &Expr, args: &Vec<Expr>) -> Result<Value, Er> {
+        let callee_val = self.eval_expr(callee)?;
+
         let mut evaled_args = vec![];
         for arg in args {
             evaled_args.push(self.eval_expr(arg)?);
         }
+        self.line = token.line;
 
-        if call.artiy() != evaled_args.len() {
-            return Err(Er::Code(1061));
+        let call = match callee_val {
+            Value::Callable(c) => c,
+            _ => {
+                // return an Error on non-callable types returned from callee
+                return Err(Er::Code(1060));
+            }
+        };
+
+        if !call.arity().accepts(evaled_args.len()) {
+            return Err(self.error(format!(
+                "Expected {} arguments but got {}.",
+                call.arity(),
+                evaled_args.len()
+            )));
         }
 
-        call.call(self, evaled_args)
+        self.budget.enter()?;
+        let res = if self.debugging.hook.is_none() {
+            call.call(self, evaled_args)
+        } else {
+            self.debugging.frames.push(Frame {
+                name: call.name().to_owned(),
+                path: self.module.clone(),
+                line: token.line,
+            });
+            let res = call.call(self, evaled_args);
+            self.debugging.frames.pop();
+            res
+        };
+        self.budget.leave();
+        res
     }
 
-    fn eval_expr(&mut self, expr: &Expr) -> Result<Value, Er> {
+    pub fn eval_expr(&mut self, expr: &Expr) -> Result<Value, Er> {
+        self.budget.step()?;
         match expr {
             Expr::Leaf(t) => self.eval_leaf(t),
-            Expr::Assign(s, rhs) => self.eval_assign(s, rhs),
+            Expr::Assign(t, rhs) => self.eval_assign(t.name(), rhs),
             Expr::Unary(t, rhs) => self.eval_unary(t, rhs),
             Expr::Binary(t, lhs, rhs) => self.eval_binary(t, lhs, rhs),
             Expr::Logical(t, lhs, rhs) => self.eval_logical(t, lhs, rhs),
             Expr::Grouping(expr) => self.eval_expr(expr),
-            Expr::Call(_, callee, args) => self.eval_call(callee, args),
+            Expr::Call(t, callee, args) => self.eval_call(t, callee, args),
+            Expr::Stringify(expr) => {
+                let text = self.eval_expr(expr)?.to_string();
+                self.budget.allocate(text.len())?;
+                Ok(Value::Text(text))
+            }
+            Expr::Conditional(cond, lhs, rhs) => self.eval_conditional(cond, lhs, rhs),
+            Expr::Postfix(t, target) => self.eval_postfix(t, target),
+            Expr::Get(t, object) => self.eval_get(t, object),
+            Expr::Set(t, object, value) => self.eval_set(t, object, value),
         }
     }
 
     fn eval_print(&mut self, expr: &Expr) -> Result<(), Er> {
         let val = self.eval_expr(expr)?;
-        println!("{}", val.to_string());
-        Ok(())
+        writeln!(self.streams.output, "{}", val)
+            .map_err(|e| self.error(format!("Could not print: {}.", e)))
     }
 
     fn eval_fun_decl(&mut self, fun: Function) -> Result<(), Er> {
-        self.envs.define(
-            fun.declaration.name.clone().as_str(),
-            Value::Callable(std::rc::Rc::new(fun)),
-        );
+        let name = fun.declaration.name.name().to_owned();
+        self.envs.define(&name, Value::Callable(std::rc::Rc::new(fun)));
         Ok(())
     }
 
-    fn eval_decl(&mut self, name: &String, expr: &Expr) -> Result<(), Er> {
+    fn eval_decl(&mut self, name: &str, expr: &Expr) -> Result<(), Er> {
         let rhs = self.eval_expr(expr)?;
-        self.envs.define(&name.as_str(), rhs);
+        self.envs.define(name, rhs);
         Ok(())
     }
 
@@ -221,13 +528,105 @@ impl Interpreter {
         Ok(())
     }
 
+    fn eval_try(&mut self, stmt: &StmtTry) -> Result<(), Er> {
+        let mut res = self.eval_block(&stmt.body);
+
+        if let (Err(er), Some((name, handler))) = (&res, &stmt.catch) {
+            res = match self.exception(er.clone()) {
+                Ok(value) => {
+                    self.envs.push_default();
+                    self.envs.define(name.name(), value);
+                    let handled = self.eval_block(handler);
+                    self.envs.pop()?;
+                    handled
+                }
+                Err(e) => Err(e),
+            };
+        }
+
+        // errors and returns from the finally block take precedence
+        if let Some(finally) = &stmt.finally {
+            self.eval_block(finally)?;
+        }
+
+        res
+    }
+
+    // Run the arm if one of its patterns matches and its guard holds, returns whether it ran.
+    // Bindings are defined in the current scope, which the caller sets up for the arm
+    fn eval_match_arm(&mut self, value: &Value, arm: &MatchArm) -> Result<bool, Er> {
+        let mut matched = false;
+        for pattern in &arm.patterns {
+            matched = match pattern {
+                Pattern::Default => true,
+                Pattern::Binding(name) => {
+                    self.envs.define(name.name(), value.clone());
+                    true
+                }
+                Pattern::Literal(expr) => is_equal(value, &self.eval_expr(expr)?),
+            };
+            if matched {
+                break;
+            }
+        }
+
+        if let (true, Some(guard)) = (matched, &arm.guard) {
+            matched = is_truthy(&self.eval_expr(guard)?);
+        }
+
+        if matched {
+            self.evaluate(&arm.body)?;
+        }
+        Ok(matched)
+    }
+
+    fn eval_match(&mut self, subject: &Expr, arms: &[MatchArm]) -> Result<(), Er> {
+        let value = self.eval_expr(subject)?;
+        for arm in arms {
+            self.envs.push_default();
+            let res = self.eval_match_arm(&value, arm);
+            self.envs.pop()?;
+            if res? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
     pub fn resolve(&mut self, expr: &Expr, depth: usize) {
         // this might just be bugged still, as Tokens in expression don't hold a reference to the lexeme, only kind and line
         // question is whether there is a case where kind and line are equal, but resolved var is different? (e.g. whole program in one line?)
         self.locals.insert(expr.clone(), depth);
     }
 
+    // Let the debugger pause before a statement, blocks pause at their statements instead
+    fn debug_statement(&mut self, stmt: &Stmt) -> Result<(), Er> {
+        let line = match stmt {
+            Stmt::Block(_) => None,
+            stmt => stmt.line(),
+        };
+        let line = match line {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        // quitting reports the line paused at
+        self.line = line;
+        // taken out while paused, so code evaluated by the debugger doesn't pause
+        match self.debugging.hook.take() {
+            Some(mut hook) => {
+                let res = hook.statement(self, stmt, line);
+                self.debugging.hook = Some(hook);
+                res
+            }
+            None => Ok(()),
+        }
+    }
+
     pub fn evaluate(&mut self, stmt: &Stmt) -> Result<(), Er> {
+        self.budget.step()?;
+        if self.debugging.hook.is_some() {
+            self.debug_statement(stmt)?;
+        }
         match stmt {
             Stmt::Expression(expr) => {
                 self.eval_expr(expr)?;
@@ -235,6 +634,7 @@ impl Interpreter {
             Stmt::Function(stmt_function) => {
                 self.eval_fun_decl(Function {
                     declaration: stmt_function.clone(),
+                    module: self.module.clone(),
                 })?;
             }
             Stmt::Return(expr) => {
@@ -244,16 +644,28 @@ impl Interpreter {
                 self.eval_print(expr)?;
             }
             Stmt::Var(token, expr) => {
-                self.eval_decl(token, expr)?;
+                self.eval_decl(token.name(), expr)?;
             }
             Stmt::Block(stmts) => {
                 self.eval_block(stmts)?;
             }
             Stmt::If(cond, lhs, rhs) => {
-                self.eval_if(cond, &*lhs, &*rhs)?;
+                self.eval_if(cond, lhs, rhs)?;
             }
             Stmt::While(cond, body) => {
-                self.eval_while(cond, &*body)?;
+                self.eval_while(cond, body)?;
+            }
+            Stmt::Throw(expr) => {
+                return Err(Er::Throw(self.eval_expr(expr)?));
+            }
+            Stmt::Try(stmt_try) => {
+                self.eval_try(stmt_try)?;
+            }
+            Stmt::Import(path, name) => {
+                self.eval_import(path, name.name())?;
+            }
+            Stmt::Match(subject, arms) => {
+                self.eval_match(subject, arms)?;
             }
         }
 
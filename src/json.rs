@@ -0,0 +1,333 @@
+use std::fmt;
+
+// A JSON value, objects keeping the order of their members
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object<K: Into<String>>(members: impl IntoIterator<Item = (K, Json)>) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        )
+    }
+
+    // The member of an object, Null if there is none
+    pub fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or(&Json::Null, |(_, value)| value),
+            _ => &Json::Null,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+            .map(|n| n as usize)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            text,
+            offset: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.offset < text.len() {
+            return Err(parser.error("Unexpected text after the value"));
+        }
+        Ok(value)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(n: f64) -> Json {
+        Json::Number(n)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Json {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Json {
+        Json::String(s.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::String(s)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(elements: Vec<T>) -> Json {
+        Json::Array(elements.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Json {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+// Compact JSON text, on a single line
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            // JSON has no infinities or NaN
+            Json::Number(n) if !n.is_finite() => write!(f, "null"),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(s) => write_string(f, s),
+            Json::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+// How deeply arrays and objects may nest, deeper ones would overflow the stack
+const MAX_DEPTH: usize = 256;
+
+struct Parser<'a> {
+    text: &'a str,
+    offset: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("{} at offset {}.", message, self.offset)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), String> {
+        if self.text[self.offset..].starts_with(word) {
+            self.offset += word.len();
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected '{}'", word)))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("Too deeply nested"));
+        }
+        self.depth += 1;
+        let value = self.element();
+        self.depth -= 1;
+        value
+    }
+
+    fn element(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('n') => self.expect("null").map(|_| Json::Null),
+            Some('t') => self.expect("true").map(|_| Json::Bool(true)),
+            Some('f') => self.expect("false").map(|_| Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => {
+                self.next();
+                let mut elements = vec![];
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.next();
+                    return Ok(Json::Array(elements));
+                }
+                loop {
+                    elements.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.next() {
+                        Some(',') => {}
+                        Some(']') => return Ok(Json::Array(elements)),
+                        _ => return Err(self.error("Expected ',' or ']'")),
+                    }
+                }
+            }
+            Some('{') => {
+                self.next();
+                let mut members = vec![];
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.next();
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != Some('"') {
+                        return Err(self.error("Expected a member name"));
+                    }
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(":")?;
+                    members.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.next() {
+                        Some(',') => {}
+                        Some('}') => return Ok(Json::Object(members)),
+                        _ => return Err(self.error("Expected ',' or '}'")),
+                    }
+                }
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.offset;
+                while matches!(self.peek(), Some('0'..='9' | '-' | '+' | '.' | 'e' | 'E')) {
+                    self.next();
+                }
+                self.text[start..self.offset]
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| self.error("Invalid number"))
+            }
+            _ => Err(self.error("Expected a value")),
+        }
+    }
+
+    // A string literal, the next character being its opening quote
+    fn string(&mut self) -> Result<String, String> {
+        self.next();
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error("Unterminated string")),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => {
+                        let high = self.hex()?;
+                        // characters outside the basic plane come as surrogate pairs
+                        let code = if (0xd800..0xdc00).contains(&high) {
+                            self.expect("\\u")?;
+                            let low = self.hex()?;
+                            0x10000 + ((high - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff)
+                        } else {
+                            high
+                        };
+                        s.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    _ => return Err(self.error("Invalid escape")),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn hex(&mut self) -> Result<u32, String> {
+        let digits = self.text.get(self.offset..self.offset + 4);
+        let code = digits.and_then(|digits| u32::from_str_radix(digits, 16).ok());
+        let code = code.ok_or_else(|| self.error("Invalid unicode escape"))?;
+        self.offset += 4;
+        Ok(code)
+    }
+}
@@ -0,0 +1,46 @@
+mod callable;
+mod cst;
+mod dap;
+mod debug;
+mod dump;
+mod environment;
+mod error;
+mod format;
+mod globals;
+mod highlight;
+mod host;
+mod interpreter;
+mod json;
+mod limits;
+mod lint;
+mod lox;
+mod lsp;
+mod native;
+mod parser;
+mod permissions;
+mod replay;
+mod resolver;
+mod scanner;
+mod stdlib;
+mod streams;
+mod token;
+mod value;
+
+pub use cst::{Element, Node, NodeKind, SyntaxTree};
+pub use dap::serve_dap;
+pub use debug::{Breakpoint, Console, Debugger, Frame, Frontend, Paused, Reason, Resume};
+pub use dump::{ast_json, ast_sexp, tokens_json, tokens_text, SYNTAX_VERSION};
+pub use error::{Diagnostic, Error};
+pub use format::format_source;
+pub use highlight::{highlight_ansi, highlight_html};
+pub use host::HostObject;
+pub use limits::{Limit, Limits};
+pub use lint::{lint_source, LintConfig, Warning};
+pub use lox::Lox;
+pub use lsp::serve_lsp;
+pub use native::{FromLox, IntoArgs, IntoLox, IntoNative, NativeFunction, Rest};
+pub use permissions::{Access, Permissions};
+pub use replay::{Event, Sources, Trace};
+pub use stdlib::collections::{List, Map};
+pub use streams::OutputBuffer;
+pub use value::Value;
@@ -0,0 +1,158 @@
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::value::Er;
+
+// How often the clock is read, in steps, as reading it costs more than counting
+const CLOCK_INTERVAL: u64 = 1024;
+
+// Call depth of the default limits. Every call takes stack space and running
+// out of it aborts the process, so unlike the others this limit is on by default
+pub const DEFAULT_DEPTH: usize = 1000;
+
+// Resources a single run may use, None meaning unlimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    // statements and expressions evaluated
+    pub steps: Option<u64>,
+    // bytes of the strings created, by concatenation, interpolation or natives,
+    // and of the items added to lists and maps
+    pub memory: Option<usize>,
+    pub time: Option<Duration>,
+    // calls in progress at once, the host thread needs the stack for them
+    pub depth: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            steps: None,
+            memory: None,
+            time: None,
+            depth: Some(DEFAULT_DEPTH),
+        }
+    }
+}
+
+// The limit a run exceeded, with its configured value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Steps(u64),
+    Memory(usize),
+    Time(Duration),
+    Depth(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Steps(max) => write!(f, "more than {} steps", max),
+            Limit::Memory(max) => write!(f, "more than {} bytes", max),
+            Limit::Time(max) => write!(f, "more than {} ms", max.as_millis()),
+            Limit::Depth(max) => write!(f, "more than {} nested calls", max),
+        }
+    }
+}
+
+// Usage of the current run, checked against the limits
+#[derive(Debug, Default)]
+pub struct Budget {
+    pub limits: Limits,
+    steps: u64,
+    // shared with the Memory handles natives get
+    memory: Rc<Cell<usize>>,
+    deadline: Option<Instant>,
+    depth: usize,
+}
+
+impl Budget {
+    // Start counting for a new run
+    pub fn reset(&mut self) {
+        self.steps = 0;
+        self.memory.set(0);
+        self.depth = 0;
+        self.deadline = self.limits.time.map(|time| Instant::now() + time);
+    }
+
+    pub fn step(&mut self) -> Result<(), Er> {
+        self.steps += 1;
+        if let Some(max) = self.limits.steps {
+            if self.steps > max {
+                return Err(Er::Limit(Limit::Steps(max)));
+            }
+        }
+        if self.steps.is_multiple_of(CLOCK_INTERVAL) {
+            if let (Some(deadline), Some(max)) = (self.deadline, self.limits.time) {
+                if Instant::now() > deadline {
+                    return Err(Er::Limit(Limit::Time(max)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Count a call starting, leave() has to follow once it returns
+    pub fn enter(&mut self) -> Result<(), Er> {
+        match self.limits.depth {
+            Some(max) if self.depth >= max => Err(Er::Limit(Limit::Depth(max))),
+            _ => {
+                self.depth += 1;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    pub fn allocate(&mut self, bytes: usize) -> Result<(), Er> {
+        self.memory.set(self.memory.get().saturating_add(bytes));
+        self.check_memory()
+    }
+
+    // Whether the memory used, also through the Memory handles, is within the limit
+    pub fn check_memory(&self) -> Result<(), Er> {
+        match self.limits.memory {
+            Some(max) if self.memory.get() > max => Err(Er::Limit(Limit::Memory(max))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn memory(&self) -> Memory {
+        Memory {
+            used: self.memory.clone(),
+            max: self.limits.memory,
+        }
+    }
+}
+
+// The memory budget as seen by natives, to check sizes before allocating. Going
+// over it fails the native, and the interpreter then stops the run
+#[derive(Debug, Clone)]
+pub struct Memory {
+    used: Rc<Cell<usize>>,
+    max: Option<usize>,
+}
+
+impl Memory {
+    // Charge bytes, e.g. for the items a list grows by
+    pub fn allocate(&self, bytes: usize) -> Result<(), String> {
+        self.used.set(self.used.get().saturating_add(bytes));
+        match self.max {
+            Some(max) if self.used.get() > max => Err("Out of memory.".to_owned()),
+            _ => Ok(()),
+        }
+    }
+
+    // Check that bytes fit before allocating them, for results charged once the
+    // native returns. Requests that don't fit are charged, to stop the run
+    pub fn reserve(&self, bytes: usize) -> Result<(), String> {
+        match self.max {
+            Some(max) if self.used.get().saturating_add(bytes) > max => self.allocate(bytes),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,459 @@
+use std::fmt;
+
+use crate::callable::Arity;
+use crate::cst::{Node, SyntaxTree};
+use crate::error::{Diagnostic, Error};
+use crate::globals::Globals;
+use crate::parser::{Expr, MatchArm, Pattern, Stmt};
+use crate::resolver::{Resolver, Symbol, SymbolKind, Symbols};
+use crate::scanner::LexemeKind;
+use crate::token::{Token, TokenType};
+use crate::value::Value;
+
+// Codes of the warnings, with what they are about
+pub const WARNINGS: &[(&str, &str)] = &[
+    ("W001", "local variable or function that is never used"),
+    ("W002", "parameter that is never used"),
+    ("W003", "statement after a return or throw"),
+    ("W004", "variable shadowing another one"),
+    ("W005", "assignment to a global that is never declared"),
+    (
+        "W006",
+        "call with an argument count the function doesn't take",
+    ),
+    ("W007", "comparison of an expression with itself"),
+    ("W008", "condition that is always true or always false"),
+];
+
+// Comments silencing warnings on their line, or on the next one when alone on
+// theirs, like `// lorx-ignore W001 W004`. Without codes they silence all warnings
+const IGNORE: &str = "lorx-ignore";
+
+// A likely mistake in a program that runs nonetheless
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub line: usize,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}] Warning {}: {}",
+            self.line, self.code, self.message
+        )
+    }
+}
+
+// Warnings turned off for all files, one code per line in the text form, where
+// `#` starts a comment:
+//
+//     # shadowing is fine here
+//     W004
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    pub disabled: Vec<String>,
+}
+
+impl LintConfig {
+    pub fn parse(text: &str) -> Result<LintConfig, Vec<Diagnostic>> {
+        let mut config = LintConfig::default();
+        let mut errors = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if WARNINGS.iter().any(|(code, _)| *code == line) {
+                config.disabled.push(line.to_owned());
+            } else {
+                errors.push(Diagnostic {
+                    line: i + 1,
+                    message: format!("Unknown warning '{}'.", line),
+                });
+            }
+        }
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// Whether both expressions are the same variable or property. Literals are left
+// out, comparing those is deliberate
+fn same(lhs: &Expr, rhs: &Expr) -> bool {
+    match (lhs, rhs) {
+        (Expr::Grouping(lhs), rhs) | (rhs, Expr::Grouping(lhs)) => same(lhs, rhs),
+        (Expr::Leaf(lhs), Expr::Leaf(rhs)) => {
+            matches!(lhs.token_type, TokenType::Identifier(_) | TokenType::This)
+                && lhs.token_type == rhs.token_type
+        }
+        (Expr::Get(lhs_name, lhs), Expr::Get(rhs_name, rhs)) => {
+            lhs_name.token_type == rhs_name.token_type && same(lhs, rhs)
+        }
+        _ => false,
+    }
+}
+
+// The truthiness of a condition made of literals only
+fn constant(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::Grouping(expr) => constant(expr),
+        Expr::Unary(
+            Token {
+                token_type: TokenType::Bang,
+                ..
+            },
+            expr,
+        ) => constant(expr).map(|truthy| !truthy),
+        Expr::Leaf(t) => match t.token_type {
+            TokenType::False | TokenType::Nil => Some(false),
+            TokenType::True | TokenType::Number(_) | TokenType::Text(_) => Some(true),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+struct Linter<'a> {
+    symbols: &'a Symbols,
+    builtins: Vec<(String, Value)>,
+    warnings: Vec<Warning>,
+}
+
+impl Linter<'_> {
+    fn warn(&mut self, line: usize, code: &'static str, message: String) {
+        self.warnings.push(Warning {
+            line,
+            code,
+            message,
+        });
+    }
+
+    fn builtin(&self, name: &str) -> Option<&Value> {
+        self.builtins
+            .iter()
+            .find(|(builtin, _)| builtin == name)
+            .map(|(_, value)| value)
+    }
+
+    fn declared(&self, token: &Token) -> bool {
+        self.symbols.of(token).is_some() || self.builtin(token.name()).is_some()
+    }
+
+    fn check_call(&mut self, callee: &Expr, args: usize) {
+        let token = match callee {
+            Expr::Leaf(
+                t @ Token {
+                    token_type: TokenType::Identifier(_),
+                    ..
+                },
+            ) => t,
+            _ => return,
+        };
+        let symbol = self.symbols.of(token);
+        let arity = match symbol.map(|symbol| &symbol.kind) {
+            Some(SymbolKind::Function(params)) => Arity::exact(params.len()),
+            Some(_) => return,
+            None => match self.builtin(token.name()) {
+                Some(Value::Callable(callable)) => callable.arity(),
+                _ => return,
+            },
+        };
+        // a global declared more than once may be either declaration
+        let globals = self.symbols.symbols.iter().filter(|other| {
+            symbol.is_some_and(|symbol| {
+                symbol.scope.is_none() && other.scope.is_none() && symbol.name() == other.name()
+            })
+        });
+        if globals.count() > 1 || arity.accepts(args) {
+            return;
+        }
+        let noun = match (arity.min, arity.max) {
+            (1, Some(1)) | (1, None) => "argument",
+            _ => "arguments",
+        };
+        let message = format!(
+            "'{}' takes {} {} but is called with {}.",
+            token.name(),
+            arity,
+            noun,
+            args
+        );
+        self.warn(token.line, "W006", message);
+    }
+
+    fn check_condition(&mut self, cond: &Expr, is_loop: bool) {
+        match constant(cond) {
+            // `while (true)` is the usual way of looping until a return or throw
+            Some(true)
+                if is_loop
+                    && matches!(
+                        cond,
+                        Expr::Leaf(Token {
+                            token_type: TokenType::True,
+                            ..
+                        })
+                    ) => {}
+            Some(truthy) => {
+                let line = cond.line();
+                self.warn(line, "W008", format!("Condition is always {}.", truthy));
+            }
+            None => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Leaf(_) => {}
+            Expr::Assign(t, value) => {
+                self.expr(value);
+                if !self.declared(t) {
+                    let message = format!("Assignment to undeclared variable '{}'.", t.name());
+                    self.warn(t.line, "W005", message);
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let comparison = matches!(
+                    op.token_type,
+                    TokenType::EqualEqual
+                        | TokenType::BangEqual
+                        | TokenType::Less
+                        | TokenType::LessEqual
+                        | TokenType::Greater
+                        | TokenType::GreaterEqual
+                );
+                if comparison && same(lhs, rhs) {
+                    let message = "Comparison of an expression with itself.".to_owned();
+                    self.warn(op.line, "W007", message);
+                }
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            Expr::Logical(_, lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            Expr::Call(_, callee, args) => {
+                self.check_call(callee, args.len());
+                self.expr(callee);
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            Expr::Conditional(cond, lhs, rhs) => {
+                self.expr(cond);
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            Expr::Set(_, object, value) => {
+                self.expr(object);
+                self.expr(value);
+            }
+            Expr::Unary(_, expr)
+            | Expr::Grouping(expr)
+            | Expr::Stringify(expr)
+            | Expr::Postfix(_, expr)
+            | Expr::Get(_, expr) => self.expr(expr),
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) {
+        // one warning for all of the statements after a return
+        let mut returned = false;
+        let mut warned = false;
+        for stmt in stmts {
+            if returned && !warned {
+                let line = stmt.line().unwrap_or_default();
+                self.warn(line, "W003", "Unreachable code.".to_owned());
+                warned = true;
+            }
+            self.stmt(stmt);
+            returned |= matches!(stmt, Stmt::Return(_) | Stmt::Throw(_));
+        }
+    }
+
+    fn arm(&mut self, arm: &MatchArm) {
+        for pattern in &arm.patterns {
+            if let Pattern::Literal(expr) = pattern {
+                self.expr(expr);
+            }
+        }
+        if let Some(guard) = &arm.guard {
+            self.expr(guard);
+        }
+        self.stmt(&arm.body);
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expression(expr)
+            | Stmt::Print(expr)
+            | Stmt::Return(expr)
+            | Stmt::Throw(expr)
+            | Stmt::Var(_, expr) => self.expr(expr),
+            Stmt::Function(function) => self.block(&function.body),
+            Stmt::Block(stmts) => self.block(stmts),
+            Stmt::If(cond, then, els) => {
+                self.check_condition(cond, false);
+                self.expr(cond);
+                self.stmt(then);
+                if let Some(els) = &**els {
+                    self.stmt(els);
+                }
+            }
+            Stmt::While(cond, body) => {
+                self.check_condition(cond, true);
+                self.expr(cond);
+                self.stmt(body);
+            }
+            Stmt::Try(stmt_try) => {
+                self.block(&stmt_try.body);
+                if let Some((_, handler)) = &stmt_try.catch {
+                    self.block(handler);
+                }
+                if let Some(finally) = &stmt_try.finally {
+                    self.block(finally);
+                }
+            }
+            Stmt::Match(subject, arms) => {
+                self.expr(subject);
+                for arm in arms {
+                    self.arm(arm);
+                }
+            }
+            Stmt::Import(..) => {}
+        }
+    }
+
+    // Warnings about the declarations themselves
+    fn symbols(&mut self) {
+        let symbols = self.symbols;
+        for symbol in &symbols.symbols {
+            let name = symbol.name();
+            let line = symbol.token.line;
+            let scope = match symbol.scope {
+                Some(scope) => scope,
+                None => continue,
+            };
+
+            // names starting with an underscore are unused on purpose
+            if symbol.references.is_empty() && !name.starts_with('_') {
+                match symbol.kind {
+                    SymbolKind::Variable | SymbolKind::Function(_) => {
+                        let message = format!("Local '{}' is never used.", name);
+                        self.warn(line, "W001", message);
+                    }
+                    SymbolKind::Parameter => {
+                        let message = format!("Parameter '{}' is never used.", name);
+                        self.warn(line, "W002", message);
+                    }
+                    _ => {}
+                }
+            }
+
+            // only names declared before this one are shadowed
+            let shadows = |other: &Symbol, scope: Option<usize>| {
+                other.scope == scope
+                    && other.name() == name
+                    && (other.token.line, other.token.col) < (line, symbol.token.col)
+            };
+            let mut outer = symbols.scopes[scope].parent;
+            let mut shadowed = None;
+            while let (Some(scope), None) = (outer, shadowed) {
+                shadowed = symbols
+                    .symbols
+                    .iter()
+                    .find(|other| shadows(other, Some(scope)));
+                outer = symbols.scopes[scope].parent;
+            }
+            let shadowed =
+                shadowed.or_else(|| symbols.symbols.iter().find(|other| shadows(other, None)));
+            if let Some(shadowed) = shadowed {
+                let message = format!(
+                    "'{}' shadows the {} declared on line {}.",
+                    name,
+                    shadowed.kind.describe(),
+                    shadowed.token.line
+                );
+                self.warn(line, "W004", message);
+            }
+        }
+    }
+}
+
+// The lines each comment silences warnings on, with the codes it silences
+fn ignored(root: &Node) -> Vec<(usize, Vec<String>)> {
+    let mut ignored = vec![];
+    // line of the last token, comments after one on the same line silence that line
+    let mut token_line = 0;
+    for lexeme in root.lexemes() {
+        match &lexeme.kind {
+            LexemeKind::Token(token) => token_line = token.line,
+            LexemeKind::Comment => {
+                let text = lexeme.text.trim_start_matches('/').trim();
+                let codes = match text.strip_prefix(IGNORE) {
+                    Some(codes) if codes.is_empty() || codes.starts_with(' ') => codes,
+                    _ => continue,
+                };
+                let line = if token_line == lexeme.line {
+                    lexeme.line
+                } else {
+                    lexeme.line + 1
+                };
+                let codes = codes
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|code| !code.is_empty())
+                    .map(str::to_owned);
+                ignored.push((line, codes.collect()));
+            }
+            _ => {}
+        }
+    }
+    ignored
+}
+
+// Warnings of a program that scanned, parsed and resolved without errors, in
+// the order of the source
+pub fn check(tree: &SyntaxTree, symbols: &Symbols, config: &LintConfig) -> Vec<Warning> {
+    let mut linter = Linter {
+        symbols,
+        builtins: Globals::new().functions,
+        warnings: vec![],
+    };
+    linter.block(tree.statements());
+    linter.symbols();
+
+    let ignored = ignored(&tree.root);
+    let mut warnings: Vec<Warning> = linter
+        .warnings
+        .into_iter()
+        .filter(|warning| !config.disabled.iter().any(|code| code == warning.code))
+        .filter(|warning| {
+            !ignored.iter().any(|(line, codes)| {
+                *line == warning.line
+                    && (codes.is_empty() || codes.iter().any(|code| code == warning.code))
+            })
+        })
+        .collect();
+    warnings.sort_by_key(|warning| warning.line);
+    warnings
+}
+
+// The warnings of a program, which must be free of syntax errors
+pub fn lint_source(source: &str, config: &LintConfig) -> Result<Vec<Warning>, Error> {
+    let mut tree = SyntaxTree::parse(source);
+    let mut resolver = Resolver::default();
+    for stmt in tree.statements() {
+        resolver.resolve_stmt(stmt);
+    }
+    tree.errors.append(&mut resolver.errors);
+    if !tree.errors.is_empty() {
+        return Err(Error::Syntax(tree.errors));
+    }
+    Ok(check(&tree, &resolver.symbols(), config))
+}
@@ -0,0 +1,229 @@
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cst::SyntaxTree;
+use crate::debug::{Debugger, Frontend};
+use crate::environment::EnvStack;
+use crate::error::{Diagnostic, Error};
+use crate::globals::Globals;
+use crate::interpreter::Interpreter;
+use crate::limits::Limits;
+use crate::native::{FromLox, IntoArgs, IntoLox};
+use crate::parser;
+use crate::permissions::Permissions;
+use crate::replay::Sources;
+use crate::resolver::Resolver;
+use crate::scanner;
+use crate::value::{describe, Er, Value};
+
+// Name of the main module when the session doesn't run a file
+const SCRIPT_NAME: &str = "<script>";
+
+// An interpreter session, globals defined by one run are visible to the next
+#[derive(Debug)]
+pub struct Lox {
+    interpreter: Interpreter,
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Lox::new()
+    }
+}
+
+impl Lox {
+    // Session with the standard library, importing relative to the working
+    // directory. Its scripts need read access to import files
+    pub fn new() -> Self {
+        let mut lox = Lox::with_path(SCRIPT_NAME);
+        lox.interpreter.root = None;
+        lox
+    }
+
+    // Session running the code of the file at path, so its imports are relative
+    // to it. Its scripts may import the files below the directory of the file
+    pub fn with_path(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let (path, root) = match path.canonicalize() {
+            Ok(path) => {
+                let root = path.parent().map(Path::to_owned);
+                (path, root)
+            }
+            Err(_) => (PathBuf::from(path), None),
+        };
+        Lox {
+            interpreter: Interpreter {
+                envs: EnvStack::with_globals(&Globals::new().functions),
+                module: path.clone(),
+                importing: vec![path],
+                root,
+                ..Default::default()
+            },
+        }
+    }
+
+    // Send the output of `print` somewhere else than stdout
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.interpreter.streams.output = Box::new(output);
+    }
+
+    // Send the output of `eprint` somewhere else than stderr
+    pub fn set_error_output(&mut self, error: impl Write + 'static) {
+        self.interpreter.streams.error = Box::new(error);
+    }
+
+    // Read the lines of `readLine` from somewhere else than stdin
+    pub fn set_input(&mut self, input: impl BufRead + 'static) {
+        self.interpreter.streams.input = Box::new(input);
+    }
+
+    // Limits for each following run_source, eval_expression or call, on their own
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.interpreter.budget.limits = limits;
+    }
+
+    // What natives may access, new sessions may access nothing
+    pub fn set_permissions(&mut self, permissions: Permissions) {
+        self.interpreter.permissions = permissions;
+    }
+
+    // Record the clock, random seed, input lines and environment variables the
+    // following runs read, or replay them from a trace
+    pub fn set_sources(&mut self, sources: Sources) {
+        self.interpreter.sources = sources;
+    }
+
+    // Let a debugger pause the following runs before their statements
+    pub fn set_debugger<F: Frontend + 'static>(&mut self, debugger: Debugger<F>) {
+        self.interpreter.debugging.hook = Some(Box::new(debugger));
+    }
+
+    // Run a program, stopping at the first runtime error
+    pub fn run_source(&mut self, source: &str) -> Result<(), Error> {
+        let mut tree = SyntaxTree::parse(source);
+        let mut resolver = Resolver::default();
+        for stmt in tree.statements() {
+            resolver.resolve_stmt(stmt);
+        }
+        tree.errors.extend(resolver.errors);
+        if !tree.errors.is_empty() {
+            return Err(Error::Syntax(tree.errors));
+        }
+
+        self.interpreter.budget.reset();
+        let result = tree
+            .statements()
+            .iter()
+            .try_for_each(|stmt| self.interpreter.evaluate(stmt));
+        self.finish(result)
+    }
+
+    // Value of a single expression, like `1 + x`, evaluated against the globals
+    pub fn eval_expression(&mut self, source: &str) -> Result<Value, Error> {
+        let (tokens, errors) = scanner::scan_tokens(source.chars());
+        if !errors.is_empty() {
+            return Err(Error::Syntax(errors));
+        }
+        let expr = parser::parse_expression(tokens).map_err(|e| Error::Syntax(vec![e]))?;
+
+        let mut resolver = Resolver::default();
+        resolver.resolve_expr(&expr);
+        if !resolver.errors.is_empty() {
+            return Err(Error::Syntax(resolver.errors));
+        }
+
+        self.interpreter.budget.reset();
+        let result = self.interpreter.eval_expr(&expr);
+        self.finish(result)
+    }
+
+    // Define or overwrite a global, e.g. to hand data or natives to scripts
+    pub fn set_global(&mut self, name: &str, value: impl IntoLox) {
+        self.interpreter.envs.define(name, value.into_lox());
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        let globals = self.interpreter.envs.globals()?;
+        globals.get(name).ok().cloned()
+    }
+
+    // Call the global function name, e.g. `let s: String = lox.call("greet", ("Ada",))?`.
+    // Problems with the call itself are reported at line 0
+    pub fn call<R: FromLox, A: IntoArgs>(&mut self, name: &str, args: A) -> Result<R, Error> {
+        let host_error = |message: String| Error::Runtime(Diagnostic { line: 0, message });
+        let function = match self.get_global(name) {
+            Some(Value::Callable(function)) => function,
+            Some(value) => {
+                return Err(host_error(format!(
+                    "Can't call '{}', it is a {}.",
+                    name,
+                    value.type_name()
+                )))
+            }
+            None => return Err(host_error(format!("Undefined variable '{}'.", name))),
+        };
+
+        let args = args.into_args();
+        if !function.arity().accepts(args.len()) {
+            return Err(host_error(format!(
+                "Expected {} arguments but got {}.",
+                function.arity(),
+                args.len()
+            )));
+        }
+
+        self.interpreter.budget.reset();
+        let result = function.call(&mut self.interpreter, args);
+        let result = self.finish(result)?;
+        let type_name = result.type_name();
+        R::from_lox(result).ok_or_else(|| {
+            host_error(format!(
+                "Expected {} as result of '{}' but got {}.",
+                R::expected(),
+                name,
+                type_name
+            ))
+        })
+    }
+
+    // End a run, writing out what it printed even when it failed, as streams
+    // such as a BufWriter<TcpStream> otherwise hold on to it
+    fn finish<T>(&mut self, result: Result<T, Er>) -> Result<T, Error> {
+        let result = result.map_err(|er| self.runtime_error(er));
+        let streams = &mut self.interpreter.streams;
+        let output = streams.output.flush();
+        let error = streams.error.flush();
+        match output.and(error) {
+            Err(e) if result.is_ok() => Err(Error::Runtime(Diagnostic {
+                line: self.interpreter.line,
+                message: format!("Could not print: {}.", e),
+            })),
+            _ => result,
+        }
+    }
+
+    fn runtime_error(&self, er: Er) -> Error {
+        let line = self.interpreter.line;
+        Error::Runtime(match er {
+            Er::Limit(limit) => return Error::ResourceLimit { limit, line },
+            Er::Stopped => return Error::Stopped { line },
+            Er::Code(code) => Diagnostic {
+                line,
+                message: describe(code).to_owned(),
+            },
+            Er::Diverged(message) => Diagnostic { line, message },
+            Er::Throw(Value::Error(e)) => Diagnostic {
+                line: e.line,
+                message: e.message.clone(),
+            },
+            Er::Throw(value) => Diagnostic {
+                line,
+                message: value.to_string(),
+            },
+            Er::Return(_) => Diagnostic {
+                line,
+                message: "Can't return from top-level code.".to_owned(),
+            },
+        })
+    }
+}
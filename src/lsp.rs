@@ -0,0 +1,478 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::cst::SyntaxTree;
+use crate::error::Diagnostic;
+use crate::globals::Globals;
+use crate::json::Json;
+use crate::lint::{self, LintConfig, Warning};
+use crate::resolver::{Resolver, Symbol, SymbolKind, Symbols};
+use crate::token::Token;
+use crate::value::Value;
+
+// JSON-RPC error codes
+const PARSE_ERROR: f64 = -32700.0;
+const INVALID_REQUEST: f64 = -32600.0;
+const METHOD_NOT_FOUND: f64 = -32601.0;
+const INVALID_PARAMS: f64 = -32602.0;
+
+// Longest message body read, so a bad Content-Length can't exhaust the memory
+const MAX_MESSAGE_LENGTH: usize = 64 << 20;
+
+// What the scanner, parser, resolver and linter found in an open document
+struct Document {
+    text: String,
+    diagnostics: Vec<Diagnostic>,
+    warnings: Vec<Warning>,
+    symbols: Symbols,
+}
+
+impl Document {
+    fn new(text: String) -> Document {
+        let tree = SyntaxTree::parse(&text);
+        let mut diagnostics = tree.errors.clone();
+
+        let mut resolver = Resolver::default();
+        for stmt in tree.statements() {
+            resolver.resolve_stmt(stmt);
+        }
+        diagnostics.append(&mut resolver.errors);
+        let symbols = resolver.symbols();
+        // statements that didn't parse would make for wrong warnings
+        let warnings = if diagnostics.is_empty() {
+            lint::check(&tree, &symbols, &LintConfig::default())
+        } else {
+            vec![]
+        };
+        Document {
+            text,
+            diagnostics,
+            warnings,
+            symbols,
+        }
+    }
+
+    fn line(&self, line: usize) -> &str {
+        self.text.lines().nth(line.saturating_sub(1)).unwrap_or("")
+    }
+
+    // LSP positions count lines from 0 and characters in UTF-16 code units,
+    // tokens lines and characters from 1
+    fn position(&self, line: usize, col: usize) -> Json {
+        let units: usize = self
+            .line(line)
+            .chars()
+            .take(col.saturating_sub(1))
+            .map(char::len_utf16)
+            .sum();
+        Json::object(vec![
+            ("line", Json::from(line.saturating_sub(1))),
+            ("character", Json::from(units)),
+        ])
+    }
+
+    // The line and column of an LSP position
+    fn locate(&self, position: &Json) -> Option<(usize, usize)> {
+        let line = position.get("line").as_usize()? + 1;
+        let mut units = position.get("character").as_usize()?;
+        let mut col = 1;
+        for c in self.line(line).chars() {
+            if units < c.len_utf16() {
+                break;
+            }
+            units -= c.len_utf16();
+            col += 1;
+        }
+        Some((line, col))
+    }
+
+    fn range(&self, token: &Token) -> Json {
+        let end = token.col + token.name().chars().count();
+        Json::object(vec![
+            ("start", self.position(token.line, token.col)),
+            ("end", self.position(token.line, end)),
+        ])
+    }
+
+    // Diagnostics only know their line, they cover its text
+    fn line_range(&self, line: usize) -> Json {
+        let text = self.line(line);
+        let indent = text.chars().take_while(|c| c.is_whitespace()).count();
+        Json::object(vec![
+            ("start", self.position(line, indent + 1)),
+            ("end", self.position(line, text.chars().count() + 1)),
+        ])
+    }
+}
+
+fn symbol_kind(kind: &SymbolKind) -> usize {
+    // numbers of the SymbolKind enumeration of the protocol
+    match kind {
+        SymbolKind::Module => 2,
+        SymbolKind::Function(_) => 12,
+        _ => 13,
+    }
+}
+
+fn completion_kind(kind: &SymbolKind) -> usize {
+    // numbers of the CompletionItemKind enumeration of the protocol
+    match kind {
+        SymbolKind::Function(_) => 3,
+        SymbolKind::Module => 9,
+        _ => 6,
+    }
+}
+
+// How a declaration reads in hovers and completions, like `function add(a, b)`
+fn signature(symbol: &Symbol) -> String {
+    match &symbol.kind {
+        SymbolKind::Function(params) => {
+            format!("function {}({})", symbol.name(), params.join(", "))
+        }
+        kind => format!("{} {}", kind.describe(), symbol.name()),
+    }
+}
+
+struct Server<W: Write> {
+    output: W,
+    documents: HashMap<String, Document>,
+    // names of the standard library, offered as completions everywhere
+    builtins: Vec<(String, Value)>,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn send(&mut self, message: Json) -> io::Result<()> {
+        write_message(&mut self.output, &message)
+    }
+
+    fn respond(&mut self, id: Json, result: Result<Json, (f64, String)>) -> io::Result<()> {
+        let outcome = match result {
+            Ok(result) => ("result", result),
+            Err((code, message)) => (
+                "error",
+                Json::object(vec![
+                    ("code", Json::from(code)),
+                    ("message", Json::from(message)),
+                ]),
+            ),
+        };
+        self.send(Json::object(vec![
+            ("jsonrpc", Json::from("2.0")),
+            ("id", id),
+            outcome,
+        ]))
+    }
+
+    fn notify(&mut self, method: &str, params: Json) -> io::Result<()> {
+        self.send(Json::object(vec![
+            ("jsonrpc", Json::from("2.0")),
+            ("method", Json::from(method)),
+            ("params", params),
+        ]))
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let mut diagnostics = vec![];
+        if let Some(document) = self.documents.get(uri) {
+            let errors = document.diagnostics.iter().map(|diagnostic| {
+                Json::object(vec![
+                    ("range", document.line_range(diagnostic.line)),
+                    ("severity", Json::from(1usize)),
+                    ("source", Json::from("lorx")),
+                    ("message", Json::from(diagnostic.message.as_str())),
+                ])
+            });
+            let warnings = document.warnings.iter().map(|warning| {
+                Json::object(vec![
+                    ("range", document.line_range(warning.line)),
+                    ("severity", Json::from(2usize)),
+                    ("code", Json::from(warning.code)),
+                    ("source", Json::from("lorx")),
+                    ("message", Json::from(warning.message.as_str())),
+                ])
+            });
+            diagnostics.extend(errors.chain(warnings));
+        }
+        self.notify(
+            "textDocument/publishDiagnostics",
+            Json::object(vec![
+                ("uri", Json::from(uri)),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        )
+    }
+
+    // A notification from the client, returns whether to keep serving
+    fn notification(&mut self, method: &str, params: &Json) -> io::Result<bool> {
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or("");
+        let uri = uri.to_owned();
+        match method {
+            "exit" => return Ok(false),
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").get("text").as_str();
+                let document = Document::new(text.unwrap_or("").to_owned());
+                self.documents.insert(uri.clone(), document);
+                self.publish_diagnostics(&uri)?;
+            }
+            // documents are synchronized in full, the last change has the whole text
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges").as_array().unwrap_or(&[]);
+                if let Some(text) = changes
+                    .last()
+                    .and_then(|change| change.get("text").as_str())
+                {
+                    self.documents
+                        .insert(uri.clone(), Document::new(text.to_owned()));
+                    self.publish_diagnostics(&uri)?;
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.publish_diagnostics(&uri)?;
+            }
+            // initialized, didSave, $/cancelRequest and the others need no answer
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (f64, String)> {
+        if method == "initialize" {
+            return Ok(Json::object(vec![
+                (
+                    "capabilities",
+                    Json::object(vec![
+                        // full text on every change
+                        ("textDocumentSync", Json::from(1usize)),
+                        ("definitionProvider", Json::from(true)),
+                        ("referencesProvider", Json::from(true)),
+                        ("hoverProvider", Json::from(true)),
+                        ("documentSymbolProvider", Json::from(true)),
+                        (
+                            "completionProvider",
+                            Json::object(Vec::<(&str, Json)>::new()),
+                        ),
+                    ]),
+                ),
+                (
+                    "serverInfo",
+                    Json::object(vec![
+                        ("name", Json::from("lorx")),
+                        ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+                    ]),
+                ),
+            ]));
+        }
+        if method == "shutdown" {
+            self.shutdown = true;
+            return Ok(Json::Null);
+        }
+
+        let documents = [
+            "textDocument/definition",
+            "textDocument/references",
+            "textDocument/hover",
+            "textDocument/documentSymbol",
+            "textDocument/completion",
+        ];
+        if !documents.contains(&method) {
+            return Err((METHOD_NOT_FOUND, format!("Unknown method '{}'.", method)));
+        }
+
+        let uri = params.get("textDocument").get("uri").as_str();
+        let document = match uri.and_then(|uri| self.documents.get(uri)) {
+            Some(document) => document,
+            None if uri.is_none() => return Err((INVALID_PARAMS, "Missing document.".into())),
+            None => return Err((INVALID_PARAMS, "Unknown document.".into())),
+        };
+        let uri = uri.unwrap_or_default();
+        let location = |token: &Token| {
+            Json::object(vec![
+                ("uri", Json::from(uri)),
+                ("range", document.range(token)),
+            ])
+        };
+        let at = document.locate(params.get("position"));
+        let symbol = at.and_then(|(line, col)| document.symbols.at(line, col));
+
+        Ok(match method {
+            "textDocument/definition" => {
+                symbol.map_or(Json::Null, |symbol| location(&symbol.token))
+            }
+            "textDocument/references" => {
+                let symbol = match symbol {
+                    Some(symbol) => symbol,
+                    None => return Ok(Json::Array(vec![])),
+                };
+                let declaration = params.get("context").get("includeDeclaration");
+                let mut tokens = vec![];
+                if declaration.as_bool() == Some(true) {
+                    tokens.push(&symbol.token);
+                }
+                tokens.extend(&symbol.references);
+                Json::Array(tokens.into_iter().map(location).collect())
+            }
+            "textDocument/hover" => symbol.map_or(Json::Null, |symbol| {
+                Json::object(vec![
+                    (
+                        "contents",
+                        Json::object(vec![
+                            ("kind", Json::from("plaintext")),
+                            ("value", Json::from(signature(symbol))),
+                        ]),
+                    ),
+                    ("range", document.range(&symbol.token)),
+                ])
+            }),
+            "textDocument/documentSymbol" => Json::Array(
+                document
+                    .symbols
+                    .symbols
+                    .iter()
+                    .filter(|symbol| {
+                        matches!(
+                            symbol.kind,
+                            SymbolKind::Variable | SymbolKind::Function(_) | SymbolKind::Module
+                        )
+                    })
+                    .map(|symbol| {
+                        Json::object(vec![
+                            ("name", Json::from(symbol.name())),
+                            ("kind", Json::from(symbol_kind(&symbol.kind))),
+                            ("location", location(&symbol.token)),
+                        ])
+                    })
+                    .collect(),
+            ),
+            // textDocument/completion
+            _ => {
+                let visible = at.map_or(vec![], |(line, col)| document.symbols.in_scope(line, col));
+                let mut names: Vec<&str> = vec![];
+                let mut items = vec![];
+                // inner declarations shadow outer ones
+                for symbol in visible {
+                    if names.contains(&symbol.name()) {
+                        continue;
+                    }
+                    names.push(symbol.name());
+                    items.push(Json::object(vec![
+                        ("label", Json::from(symbol.name())),
+                        ("kind", Json::from(completion_kind(&symbol.kind))),
+                        ("detail", Json::from(signature(symbol))),
+                    ]));
+                }
+                for (name, value) in &self.builtins {
+                    if names.contains(&name.as_str()) {
+                        continue;
+                    }
+                    let (kind, detail) = match value {
+                        Value::Callable(_) => (3usize, "native function"),
+                        _ => (21, "constant"),
+                    };
+                    items.push(Json::object(vec![
+                        ("label", Json::from(name.as_str())),
+                        ("kind", Json::from(kind)),
+                        ("detail", Json::from(detail)),
+                    ]));
+                }
+                Json::Array(items)
+            }
+        })
+    }
+
+    // Handle a message, returns whether to keep serving
+    fn message(&mut self, body: &str) -> io::Result<bool> {
+        let message = match Json::parse(body) {
+            Ok(message) => message,
+            Err(e) => {
+                self.respond(Json::Null, Err((PARSE_ERROR, e)))?;
+                return Ok(true);
+            }
+        };
+        let method = match message.get("method").as_str() {
+            Some(method) => method,
+            // a response to a request of ours, we send none
+            None if message.get("id") != &Json::Null && message.get("result") != &Json::Null => {
+                return Ok(true)
+            }
+            None => {
+                let id = message.get("id").clone();
+                self.respond(id, Err((INVALID_REQUEST, "Missing method.".into())))?;
+                return Ok(true);
+            }
+        };
+        let params = message.get("params");
+        match message.get("id") {
+            Json::Null => self.notification(method, params),
+            id => {
+                let result = self.request(method, params);
+                self.respond(id.clone(), result)?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+// The body of the next message, None at the end of the input. The Debug
+// Adapter Protocol frames its messages the same way
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            // blank lines between messages are tolerated, for hand written scripts
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length = length.unwrap_or(0);
+    if length > MAX_MESSAGE_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "message of {} bytes, more than the {} allowed",
+                length, MAX_MESSAGE_LENGTH
+            ),
+        ));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+// Serve the Language Server Protocol over a pair of streams, usually stdin and
+// stdout, until the client sends `exit` or closes the input. Returns whether
+// the client asked for the shutdown first
+pub fn serve_lsp(mut input: impl BufRead, output: impl Write) -> io::Result<bool> {
+    let mut server = Server {
+        output,
+        documents: HashMap::new(),
+        builtins: Globals::new().functions,
+        shutdown: false,
+    };
+    while let Some(body) = read_message(&mut input)? {
+        if !server.message(&body)? {
+            break;
+        }
+    }
+    Ok(server.shutdown)
+}
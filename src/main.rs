@@ -1,62 +1,462 @@
 use std::env;
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::BufReader;
-
-mod callable;
-mod environment;
-mod globals;
-mod interpreter;
-mod parser;
-mod scanner;
-mod token;
-mod value;
-mod resolver;
-
-const DEFAULT_PROGRAM_PATH: &'static str = "./programs/testProgram.lox";
-
-fn read_program() -> std::io::Result<String> {
-    // Prints each argument on a separate line
-    // if env::args().len() != 2 {
-    //     panic!("Expected exactly one argument!");
-    // }
-
-    let path = env::args()
-        .nth(1)
-        .unwrap_or(DEFAULT_PROGRAM_PATH.to_owned());
-
-    let file = File::open(path)?;
-    let mut buf_reader = BufReader::new(file);
-    let mut contents = String::new();
-    buf_reader.read_to_string(&mut contents)?;
-    Ok(contents)
-}
-
-fn main() -> std::io::Result<()> {
-    let text = read_program()?;
-    let tokens = scanner::scan_tokens(text.chars()); // todo handle tokenization errors
-    println!("{:?}", tokens);
-    let parse_tree = parser::parse(tokens);
-    println!("{:#?}", parse_tree);
-    let mut interpreter = interpreter::Interpreter {
-        envs: environment::EnvStack::<value::Value>::with_globals(&(globals::Globals::new().functions)),
-        locals: Default::default(),
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+use lorx::{
+    ast_json, ast_sexp, format_source, highlight_ansi, highlight_html, lint_source, serve_dap,
+    serve_lsp, tokens_json, tokens_text, Access, Console, Debugger, Error, Limits, LintConfig, Lox,
+    Permissions, Sources, Trace,
+};
+
+const DEFAULT_PROGRAM_PATH: &str = "./programs/testProgram.lox";
+
+const USAGE: &str = "Usage: lorx [options] [path]
+       lorx fmt [--check] [paths]
+       lorx lint [--config FILE] [paths]
+       lorx tokens [--json] [path]
+       lorx ast [--json] [path]
+       lorx highlight [--html] [path]
+       lorx lsp
+       lorx debug [options] path
+       lorx dap
+
+Commands:
+  fmt                    format the files in place, or stdin to stdout
+  fmt --check            only list the files that aren't formatted
+  lint                   warn about likely mistakes in the files, or stdin
+  lint --config FILE     turn off the warnings listed in FILE, by default .lorxlint
+                         if there is one; `// lorx-ignore W001` silences them per line
+  tokens                 list the tokens and comments of the file, or stdin
+  ast                    print the syntax tree of the file, or stdin, as S-expressions
+  tokens/ast --json      print them as JSON instead of text and S-expressions, with
+                         source spans and a schema version described in src/dump.rs
+  highlight              print the file, or stdin, coloured for terminals
+  highlight --html       print it as an HTML page, with a CSS class per kind of token
+  lsp                    serve the Language Server Protocol on stdin and stdout
+  debug                  run the file paused before its first statement, taking
+                         commands like `break 12`, `next` and `print x`, see `help`
+  dap                    serve the Debug Adapter Protocol on stdin and stdout
+
+Limits:
+  --max-steps N          stop after evaluating N statements and expressions
+  --max-memory BYTES     stop after creating strings and list or map items of
+                         BYTES bytes in total
+  --timeout MS           stop after MS milliseconds
+  --max-depth N          stop at more than N nested calls, 1000 by default
+
+Permissions, scripts get none unless granted:
+  --allow-read[=PATHS]   read files, only below the comma separated PATHS if given,
+                         importing files below the script's directory is allowed
+  --allow-write[=PATHS]  write files, only below the comma separated PATHS if given
+  --allow-env            read environment variables
+  --allow-clock          read the time
+  --allow-all            all of the above
+
+Replay, of the clock, random seed, input lines and environment variables read:
+  --record FILE          write what the run reads to FILE
+  --replay FILE          read it from FILE, as written by --record, instead";
+
+// exit codes of sysexits.h, as used by the reference implementation
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_SOFTWARE: i32 = 70;
+const EX_IOERR: i32 = 74;
+// not from sysexits.h, lets callers tell runaway scripts from failing ones
+const EX_LIMIT: i32 = 75;
+// like other formatters, for `fmt --check` finding unformatted files
+const EX_UNFORMATTED: i32 = 1;
+// like other linters, for `lint` finding something to warn about
+const EX_WARNINGS: i32 = 1;
+
+// Stack of the thread running the commands. Lox calls nest Rust calls, a debug
+// build needs tens of kilobytes per call in deeply nested code
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+// Lint configuration used when no --config is given, if it exists
+const LINT_CONFIG: &str = ".lorxlint";
+
+struct Options {
+    path: PathBuf,
+    limits: Limits,
+    permissions: Permissions,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+}
+
+// `--allow-read` grants access to all files, `--allow-read=a,b` only to a and b
+fn access(paths: Option<&str>) -> Access {
+    match paths {
+        None => Access::All,
+        Some(paths) => Access::Paths(paths.split(',').map(PathBuf::from).collect()),
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(EX_USAGE);
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Options {
+    let mut path = None;
+    let mut limits = Limits::default();
+    let mut permissions = Permissions::default();
+    let (mut record, mut replay) = (None, None);
+    while let Some(arg) = args.next() {
+        // values may follow as `--flag=value`, those of limits and files also as
+        // `--flag value`
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_owned())),
+            None => (arg.as_str(), None),
+        };
+        let mut value_or_next = || {
+            value
+                .clone()
+                .or_else(|| args.next())
+                .unwrap_or_else(|| usage_error(&format!("Missing value of {}.", flag)))
+        };
+        let mut number = || -> u64 {
+            let value = value_or_next();
+            value.parse().unwrap_or_else(|_| {
+                usage_error(&format!("Invalid value of {}: '{}'.", flag, value))
+            })
+        };
+        match flag {
+            "--max-steps" => limits.steps = Some(number()),
+            "--max-memory" => limits.memory = Some(number() as usize),
+            "--timeout" => limits.time = Some(Duration::from_millis(number())),
+            "--max-depth" => limits.depth = Some(number() as usize),
+            "--allow-read" => permissions.read = access(value.as_deref()),
+            "--allow-write" => permissions.write = access(value.as_deref()),
+            "--allow-env" | "--allow-clock" | "--allow-all" if value.is_some() => {
+                usage_error(&format!("{} takes no value.", flag))
+            }
+            "--allow-env" => permissions.env = true,
+            "--allow-clock" => permissions.clock = true,
+            "--allow-all" => permissions = Permissions::all(),
+            "--record" => record = Some(PathBuf::from(value_or_next())),
+            "--replay" => replay = Some(PathBuf::from(value_or_next())),
+            _ if flag.starts_with("--") => usage_error(&format!("Unknown option '{}'.", flag)),
+            _ if path.is_some() => usage_error("Expected a single program path."),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    if record.is_some() && replay.is_some() {
+        usage_error("Expected either --record or --replay, not both.");
+    }
+    Options {
+        path: path.unwrap_or_else(|| PathBuf::from(DEFAULT_PROGRAM_PATH)),
+        limits,
+        permissions,
+        record,
+        replay,
+    }
+}
+
+fn read_source(path: &Path) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Could not read '{}': {}.", path.display(), e);
+        process::exit(EX_IOERR);
+    })
+}
+
+fn read_stdin() -> String {
+    let mut source = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut source) {
+        eprintln!("Could not read stdin: {}.", e);
+        process::exit(EX_IOERR);
+    }
+    source
+}
+
+fn fmt(args: impl Iterator<Item = String>) {
+    let mut check = false;
+    let mut paths = vec![];
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown option '{}'.", arg)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if paths.is_empty() {
+        let source = read_stdin();
+        match format_source(&source) {
+            Ok(formatted) if check && formatted != source => process::exit(EX_UNFORMATTED),
+            Ok(_) if check => {}
+            Ok(formatted) => print!("{}", formatted),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(EX_DATAERR);
+            }
+        }
+        return;
+    }
+
+    let mut status = 0;
+    for path in paths {
+        let source = read_source(&path);
+        let formatted = match format_source(&source) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                for line in e.to_string().lines() {
+                    eprintln!("{}: {}", path.display(), line);
+                }
+                status = EX_DATAERR;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", path.display());
+            status = status.max(EX_UNFORMATTED);
+        } else if let Err(e) = std::fs::write(&path, formatted) {
+            eprintln!("Could not write '{}': {}.", path.display(), e);
+            process::exit(EX_IOERR);
+        }
+    }
+    process::exit(status);
+}
+
+fn lint(mut args: impl Iterator<Item = String>) {
+    let mut config = None;
+    let mut paths = vec![];
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_owned())),
+            None => (arg.as_str(), None),
+        };
+        match flag {
+            "--config" => {
+                let value = value.or_else(|| args.next());
+                let value = value.unwrap_or_else(|| usage_error("Missing value of --config."));
+                config = Some(PathBuf::from(value));
+            }
+            _ if flag.starts_with("--") => usage_error(&format!("Unknown option '{}'.", flag)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    let config = match config {
+        Some(path) => Some(path),
+        None => Some(PathBuf::from(LINT_CONFIG)).filter(|path| path.exists()),
+    };
+    let config = match config {
+        Some(path) => LintConfig::parse(&read_source(&path)).unwrap_or_else(|errors| {
+            for error in errors {
+                eprintln!("{}: {}", path.display(), error);
+            }
+            process::exit(EX_DATAERR);
+        }),
+        None => LintConfig::default(),
+    };
+
+    let sources = if paths.is_empty() {
+        vec![(None, read_stdin())]
+    } else {
+        paths
+            .into_iter()
+            .map(|path| {
+                let source = read_source(&path);
+                (Some(path), source)
+            })
+            .collect()
+    };
+
+    let mut status = 0;
+    for (path, source) in sources {
+        let prefix = path.map_or(String::new(), |path| format!("{}: ", path.display()));
+        match lint_source(&source, &config) {
+            Ok(warnings) => {
+                for warning in &warnings {
+                    println!("{}{}", prefix, warning);
+                }
+                if !warnings.is_empty() {
+                    status = status.max(EX_WARNINGS);
+                }
+            }
+            Err(e) => {
+                for line in e.to_string().lines() {
+                    eprintln!("{}{}", prefix, line);
+                }
+                status = EX_DATAERR;
+            }
+        }
+    }
+    process::exit(status);
+}
+
+// Whether --json was given, and the source of the path or stdin
+fn dump_options(args: impl Iterator<Item = String>) -> (bool, String) {
+    let mut json = false;
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown option '{}'.", arg)),
+            _ if path.is_some() => usage_error("Expected a single path."),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+    let source = match path {
+        Some(path) => read_source(&path),
+        None => read_stdin(),
     };
+    (json, source)
+}
+
+fn tokens(args: impl Iterator<Item = String>) {
+    // text that doesn't scan is in the output as error tokens
+    match dump_options(args) {
+        (true, source) => println!("{}", tokens_json(&source)),
+        (false, source) => print!("{}", tokens_text(&source)),
+    }
+}
+
+fn ast(args: impl Iterator<Item = String>) {
+    let (json, source) = dump_options(args);
+    let dump = if json {
+        ast_json(&source).map(|dump| format!("{}\n", dump))
+    } else {
+        ast_sexp(&source)
+    };
+    match dump {
+        Ok(dump) => print!("{}", dump),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(EX_DATAERR);
+        }
+    }
+}
+
+fn highlight(args: impl Iterator<Item = String>) {
+    let mut html = false;
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--html" => html = true,
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown option '{}'.", arg)),
+            _ if path.is_some() => usage_error("Expected a single path."),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+    let (title, source) = match &path {
+        Some(path) => (path.display().to_string(), read_source(path)),
+        None => ("stdin".to_owned(), read_stdin()),
+    };
+    // text that doesn't scan is highlighted as an error, there is nothing to fail
+    if html {
+        print!("{}", highlight_html(&source, &title));
+    } else {
+        print!("{}", highlight_ansi(&source));
+    }
+}
 
-    {
-        let mut resolver = resolver::Resolver::default();
-        for stmt in &parse_tree {
-            resolver.resolve_stmt(stmt);
+fn lsp(mut args: impl Iterator<Item = String>) {
+    if let Some(arg) = args.next() {
+        usage_error(&format!("Unexpected argument '{}'.", arg));
+    }
+    let stdin = std::io::stdin();
+    match serve_lsp(stdin.lock(), std::io::stdout()) {
+        // the protocol has clients exit with 1 when they didn't shut the server down first
+        Ok(shutdown) => process::exit(if shutdown { 0 } else { 1 }),
+        Err(e) => {
+            eprintln!("Could not serve: {}.", e);
+            process::exit(EX_IOERR);
         }
     }
+}
+
+fn dap(mut args: impl Iterator<Item = String>) {
+    if let Some(arg) = args.next() {
+        usage_error(&format!("Unexpected argument '{}'.", arg));
+    }
+    if let Err(e) = serve_dap(std::io::stdin().lock(), std::io::stdout()) {
+        eprintln!("Could not serve: {}.", e);
+        process::exit(EX_IOERR);
+    }
+}
 
-    for stmt in parse_tree {
-        let res = interpreter.evaluate(&stmt);
-        if res.is_err() {
-            println!("{:#?}", res);
-            println!("{:#?}", interpreter);
+fn read_trace(path: &Path) -> Trace {
+    let text = read_source(path);
+    Trace::parse(&text).unwrap_or_else(|errors| {
+        for error in errors {
+            eprintln!("{}: {}", path.display(), error);
         }
+        process::exit(EX_DATAERR);
+    })
+}
+
+// Run the program of the options, with the debugger on the terminal if debug
+fn run(options: Options, debug: bool) {
+    let path = options.path;
+    let text = read_source(&path);
+
+    let mut lox = Lox::with_path(&path);
+    lox.set_limits(options.limits);
+    lox.set_permissions(options.permissions);
+    if let Some(replay) = &options.replay {
+        lox.set_sources(Sources::Replay(read_trace(replay)));
+    }
+    let recorded = Trace::new();
+    if options.record.is_some() {
+        lox.set_sources(Sources::Record(recorded.clone()));
+    }
+    if debug {
+        let mut console = Console::new(std::io::stdin().lock(), std::io::stdout());
+        console.set_colors(std::io::stdout().is_terminal());
+        lox.set_debugger(Debugger::new(console, vec![], true));
+    }
+
+    let result = lox.run_source(&text);
+    // also when the run failed, that's the one worth replaying
+    if let Some(record) = &options.record {
+        if let Err(e) = std::fs::write(record, recorded.to_string()) {
+            eprintln!("Could not write '{}': {}.", record.display(), e);
+            process::exit(EX_IOERR);
+        }
+    }
+    match result {
+        // quitting the debugger isn't a failure
+        Ok(()) | Err(Error::Stopped { .. }) => {}
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(match e {
+                Error::Syntax(_) => EX_DATAERR,
+                Error::Runtime(_) | Error::Stopped { .. } => EX_SOFTWARE,
+                Error::ResourceLimit { .. } => EX_LIMIT,
+            });
+        }
+    }
+}
+
+fn command() {
+    let mut args = env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("fmt") => fmt(args.skip(1)),
+        Some("lint") => lint(args.skip(1)),
+        Some("tokens") => tokens(args.skip(1)),
+        Some("ast") => ast(args.skip(1)),
+        Some("highlight") => highlight(args.skip(1)),
+        Some("lsp") => lsp(args.skip(1)),
+        Some("debug") => run(parse_options(args.skip(1)), true),
+        Some("dap") => dap(args.skip(1)),
+        _ => run(parse_options(args), false),
+    }
+}
+
+fn main() {
+    let thread = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(command)
+        .expect("could not start the interpreter thread");
+    // a panic has been reported already, exit like the main thread would
+    if thread.join().is_err() {
+        process::exit(101);
     }
-    println!("{:#?}", interpreter);
-    Ok(())
 }
@@ -0,0 +1,506 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::callable::{Arity, Callable};
+use crate::host::HostObject;
+use crate::interpreter::Interpreter;
+use crate::limits::Memory;
+use crate::permissions::Permissions;
+use crate::replay::Sources;
+use crate::stdlib::collections::{List, Map};
+use crate::value::{Er, Value};
+
+// Conversion of a Lox value into a Rust type, e.g. for the parameters of native functions
+pub trait FromLox: Sized {
+    // Type named in errors when the conversion fails, like "number"
+    fn expected() -> String;
+    fn from_lox(value: Value) -> Option<Self>;
+    // Value of an argument that was left out, only optional types have one
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+pub trait IntoLox {
+    fn into_lox(self) -> Value;
+}
+
+impl FromLox for f64 {
+    fn expected() -> String {
+        "number".to_owned()
+    }
+    fn from_lox(value: Value) -> Option<Self> {
+        match value {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+}
+
+impl FromLox for String {
+    fn expected() -> String {
+        "string".to_owned()
+    }
+    fn from_lox(value: Value) -> Option<Self> {
+        match value {
+            Value::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl FromLox for bool {
+    fn expected() -> String {
+        "bool".to_owned()
+    }
+    fn from_lox(value: Value) -> Option<Self> {
+        match value {
+            Value::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+impl FromLox for Value {
+    fn expected() -> String {
+        "value".to_owned()
+    }
+    fn from_lox(value: Value) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl FromLox for Rc<dyn Callable> {
+    fn expected() -> String {
+        "function".to_owned()
+    }
+    fn from_lox(value: Value) -> Option<Self> {
+        match value {
+            Value::Callable(c) => Some(c),
+            _ => None,
+        }
+    }
+}
+
+impl FromLox for Rc<dyn HostObject> {
+    fn expected() -> String {
+        "instance".to_owned()
+    }
+    fn from_lox(value: Value) -> Option<Self> {
+        match value {
+            Value::Host(h) => Some(h),
+            _ => None,
+        }
+    }
+}
+
+// Discards the value, e.g. for calling a Lox function only for its effects
+impl FromLox for () {
+    fn expected() -> String {
+        "value".to_owned()
+    }
+    fn from_lox(_: Value) -> Option<Self> {
+        Some(())
+    }
+}
+
+// Integers are numbers without a fractional part that fit the type. The bound
+// is MAX + 1, a power of two and so exact, where MAX of the 64-bit types would
+// round up to it as a number and let it through
+macro_rules! impl_integer {
+    ($($int:ty),*) => {$(
+        impl FromLox for $int {
+            fn expected() -> String {
+                "integer".to_owned()
+            }
+            fn from_lox(value: Value) -> Option<Self> {
+                match value {
+                    Value::Number(n)
+                        if n.fract() == 0.0
+                            && n >= <$int>::MIN as f64
+                            && n < <$int>::MAX as f64 + 1.0 =>
+                    {
+                        Some(n as $int)
+                    }
+                    _ => None,
+                }
+            }
+        }
+
+        impl IntoLox for $int {
+            fn into_lox(self) -> Value {
+                Value::Number(self as f64)
+            }
+        }
+    )*};
+}
+
+impl_integer!(i32, i64, u32, u64, usize);
+
+// Copies the items, changes made by Lox code after the call don't show up
+impl<T: FromLox> FromLox for Vec<T> {
+    fn expected() -> String {
+        format!("list of {}", T::expected())
+    }
+    fn from_lox(value: Value) -> Option<Self> {
+        let host: Rc<dyn std::any::Any> = Rc::<dyn HostObject>::from_lox(value)?;
+        let list = host.downcast::<List>().ok()?;
+        let items = list.items.borrow();
+        items.iter().cloned().map(T::from_lox).collect()
+    }
+}
+
+impl<T: FromLox> FromLox for HashMap<String, T> {
+    fn expected() -> String {
+        format!("map of {}", T::expected())
+    }
+    fn from_lox(value: Value) -> Option<Self> {
+        let host: Rc<dyn std::any::Any> = Rc::<dyn HostObject>::from_lox(value)?;
+        let map = host.downcast::<Map>().ok()?;
+        let entries = map.entries.borrow();
+        entries
+            .iter()
+            .map(|(key, value)| Some((key.clone(), T::from_lox(value.clone())?)))
+            .collect()
+    }
+}
+
+// Optional parameters may be left out or passed nil
+impl<T: FromLox> FromLox for Option<T> {
+    fn expected() -> String {
+        format!("{} or nil", T::expected())
+    }
+    fn from_lox(value: Value) -> Option<Self> {
+        match value {
+            Value::Nil => Some(None),
+            value => T::from_lox(value).map(Some),
+        }
+    }
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl IntoLox for f64 {
+    fn into_lox(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl IntoLox for String {
+    fn into_lox(self) -> Value {
+        Value::Text(self)
+    }
+}
+
+impl IntoLox for &str {
+    fn into_lox(self) -> Value {
+        Value::Text(self.to_owned())
+    }
+}
+
+impl IntoLox for bool {
+    fn into_lox(self) -> Value {
+        Value::Boolean(self)
+    }
+}
+
+impl IntoLox for Value {
+    fn into_lox(self) -> Value {
+        self
+    }
+}
+
+impl IntoLox for () {
+    fn into_lox(self) -> Value {
+        Value::Nil
+    }
+}
+
+impl IntoLox for Rc<dyn HostObject> {
+    fn into_lox(self) -> Value {
+        Value::Host(self)
+    }
+}
+
+impl<T: HostObject + 'static> IntoLox for Rc<T> {
+    fn into_lox(self) -> Value {
+        Value::Host(self)
+    }
+}
+
+impl<T: IntoLox> IntoLox for Vec<T> {
+    fn into_lox(self) -> Value {
+        let items = self.into_iter().map(T::into_lox).collect();
+        Value::Host(Rc::new(List::new(items)))
+    }
+}
+
+impl<T: IntoLox> IntoLox for HashMap<String, T> {
+    fn into_lox(self) -> Value {
+        let entries = self.into_iter().map(|(k, v)| (k, v.into_lox())).collect();
+        Value::Host(Rc::new(Map::new(entries)))
+    }
+}
+
+impl<T: IntoLox> IntoLox for Option<T> {
+    fn into_lox(self) -> Value {
+        self.map_or(Value::Nil, T::into_lox)
+    }
+}
+
+// Arguments for calling a Lox function from Rust, a tuple of values to convert
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<Value>;
+}
+
+impl IntoArgs for Vec<Value> {
+    fn into_args(self) -> Vec<Value> {
+        self
+    }
+}
+
+macro_rules! impl_into_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: IntoLox),*> IntoArgs for ($($arg,)*) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Vec<Value> {
+                let ($($arg,)*) = self;
+                vec![$($arg.into_lox()),*]
+            }
+        }
+    };
+}
+
+impl_into_args!();
+impl_into_args!(A);
+impl_into_args!(A, B);
+impl_into_args!(A, B, C);
+impl_into_args!(A, B, C, D);
+impl_into_args!(A, B, C, D, E);
+
+// Arguments of a native call, taken by its parameters one after another
+pub struct Args<'a> {
+    name: &'a str,
+    values: std::vec::IntoIter<Value>,
+    position: usize,
+}
+
+impl<'a> Args<'a> {
+    pub fn new(name: &'a str, values: Vec<Value>) -> Self {
+        Args {
+            name,
+            values: values.into_iter(),
+            position: 0,
+        }
+    }
+
+    pub fn next<T: FromLox>(&mut self) -> Result<T, String> {
+        self.position += 1;
+        let value = match self.values.next() {
+            Some(value) => value,
+            None => return T::missing().ok_or_else(|| format!("Missing argument {}.", self.position)),
+        };
+
+        let type_name = value.type_name();
+        T::from_lox(value).ok_or_else(|| {
+            format!(
+                "Expected {} as argument {} of '{}' but got {}.",
+                T::expected(),
+                self.position,
+                self.name,
+                type_name
+            )
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.values.len() == 0
+    }
+}
+
+// Last parameter of a variadic native, collecting all remaining arguments
+pub struct Rest<T>(pub Vec<T>);
+
+// Parameter of a native function, taking one argument or, for Rest, all remaining ones
+pub trait Param: Sized {
+    fn arity() -> Arity;
+    fn from_args(args: &mut Args, interpreter: &Interpreter) -> Result<Self, String>;
+}
+
+impl<T: FromLox> Param for T {
+    fn arity() -> Arity {
+        Arity {
+            min: if T::missing().is_some() { 0 } else { 1 },
+            max: Some(1),
+        }
+    }
+    fn from_args(args: &mut Args, _: &Interpreter) -> Result<Self, String> {
+        args.next()
+    }
+}
+
+impl<T: FromLox> Param for Rest<T> {
+    fn arity() -> Arity {
+        Arity { min: 0, max: None }
+    }
+    fn from_args(args: &mut Args, _: &Interpreter) -> Result<Self, String> {
+        let mut rest = vec![];
+        while !args.is_empty() {
+            rest.push(args.next()?);
+        }
+        Ok(Rest(rest))
+    }
+}
+
+// The permissions of the running script, taking no argument
+impl Param for Permissions {
+    fn arity() -> Arity {
+        Arity::exact(0)
+    }
+    fn from_args(_: &mut Args, interpreter: &Interpreter) -> Result<Self, String> {
+        Ok(interpreter.permissions.clone())
+    }
+}
+
+// The memory budget of the running script, taking no argument
+impl Param for Memory {
+    fn arity() -> Arity {
+        Arity::exact(0)
+    }
+    fn from_args(_: &mut Args, interpreter: &Interpreter) -> Result<Self, String> {
+        Ok(interpreter.budget.memory())
+    }
+}
+
+// Where the running script's nondeterministic inputs come from, taking no argument
+impl Param for Sources {
+    fn arity() -> Arity {
+        Arity::exact(0)
+    }
+    fn from_args(_: &mut Args, interpreter: &Interpreter) -> Result<Self, String> {
+        Ok(interpreter.sources.clone())
+    }
+}
+
+// Return type of a native function, Err(message) raises a runtime error
+pub trait NativeResult {
+    fn into_result(self) -> Result<Value, String>;
+}
+
+impl<T: IntoLox> NativeResult for T {
+    fn into_result(self) -> Result<Value, String> {
+        Ok(self.into_lox())
+    }
+}
+
+impl<T: IntoLox> NativeResult for Result<T, String> {
+    fn into_result(self) -> Result<Value, String> {
+        self.map(T::into_lox)
+    }
+}
+
+type NativeFn = Rc<dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, Er>>;
+
+// Rust function or closure with typed parameters that can be called from Lox,
+// Params is the tuple of its parameter types
+pub trait IntoNative<Params> {
+    fn arity() -> Arity;
+    fn into_native(self, name: &str) -> NativeFn;
+}
+
+macro_rules! impl_into_native {
+    ($($param:ident),*) => {
+        impl<F, R, $($param),*> IntoNative<($($param,)*)> for F
+        where
+            F: Fn($($param),*) -> R + 'static,
+            R: NativeResult,
+            $($param: Param),*
+        {
+            fn arity() -> Arity {
+                Arity::exact(0)$(.then($param::arity()))*
+            }
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn into_native(self, name: &str) -> NativeFn {
+                let name = name.to_owned();
+                Rc::new(move |interpreter, values| {
+                    let mut args = Args::new(&name, values);
+                    $(let $param = $param::from_args(&mut args, interpreter).map_err(|e| interpreter.error(e))?;)*
+                    self($($param),*).into_result().map_err(|e| interpreter.error(e))
+                })
+            }
+        }
+    };
+}
+
+impl_into_native!();
+impl_into_native!(A);
+impl_into_native!(A, B);
+impl_into_native!(A, B, C);
+impl_into_native!(A, B, C, D);
+impl_into_native!(A, B, C, D, E);
+
+pub struct NativeFunction {
+    pub name: String,
+    arity: Arity,
+    function: NativeFn,
+}
+
+impl NativeFunction {
+    // Wrap a Rust function, checking argument count and types before calling it
+    pub fn new<Params, F: IntoNative<Params>>(name: &str, function: F) -> Self {
+        NativeFunction {
+            name: name.to_owned(),
+            arity: F::arity(),
+            function: function.into_native(name),
+        }
+    }
+
+    // For natives that need the interpreter or check their arguments themselves
+    pub(crate) fn raw<F>(name: &str, arity: Arity, function: F) -> Self
+    where
+        F: Fn(&mut Interpreter, Vec<Value>) -> Result<Value, Er> + 'static,
+    {
+        NativeFunction {
+            name: name.to_owned(),
+            arity,
+            function: Rc::new(function),
+        }
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
+
+impl Callable for NativeFunction {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Er> {
+        let value = (self.function)(interpreter, args);
+        // natives fail when they go over the memory budget, that stops the run
+        // rather than raising their error
+        interpreter.budget.check_memory()?;
+        // as does a replay diverging
+        interpreter.sources.check_diverged()?;
+        let value = value?;
+        if let Value::Text(text) = &value {
+            interpreter.budget.allocate(text.len())?;
+        }
+        Ok(value)
+    }
+}
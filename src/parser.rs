@@ -1,48 +1,231 @@
+use crate::cst::NodeKind;
+use crate::error::Diagnostic;
 use crate::token::{Token, TokenType};
 use std::iter::Peekable;
+use std::path::Path;
+use std::vec;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Expr {
     Leaf(Token),
     // Variable(Token), // probably won't need this as we turn "var x;" into "var x = null;"?
-    Assign(String, Box<Expr>),
+    // the token is the assigned variable
+    Assign(Token, Box<Expr>),
     Unary(Token, Box<Expr>),
     Binary(Token, Box<Expr>, Box<Expr>),
     Logical(Token, Box<Expr>, Box<Expr>),
     Grouping(Box<Expr>),
     Call(Token, Box<Expr>, Vec<Expr>),
+    // Converts the value to a Text, used for expressions embedded in string literals
+    Stringify(Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    // `a++` and `a--`, prefix forms are desugared into assignments
+    Postfix(Token, Box<Expr>),
+    // Property access, the token being the property name
+    Get(Token, Box<Expr>),
+    // Property assignment `object.name = value`
+    Set(Token, Box<Expr>, Box<Expr>),
 }
 
 #[derive(Debug, Clone)]
 pub struct StmtFunction {
-    pub name: String,
-    pub params: Vec<String>,
+    pub name: Token,
+    pub params: Vec<Token>,
     pub body: Vec<Stmt>,
 }
 
+#[derive(Debug, Clone)]
+pub struct StmtTry {
+    pub body: Vec<Stmt>,
+    // name the exception is bound to, and the handler
+    pub catch: Option<(Token, Vec<Stmt>)>,
+    pub finally: Option<Vec<Stmt>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    // Number, string, boolean or nil literal, compared for equality
+    Literal(Expr),
+    // Matches anything and binds it to the name, for use in the guard and body
+    Binding(Token),
+    Default,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub patterns: Vec<Pattern>,
+    pub guard: Option<Expr>,
+    pub body: Stmt,
+}
+
 #[derive(Debug, Clone)]
 pub enum Stmt {
     Expression(Expr),
     Function(StmtFunction),
     Return(Expr),
     Print(Expr),
-    Var(String, Expr),
+    Var(Token, Expr),
     Block(Vec<Stmt>),
     If(Expr, Box<Stmt>, Box<Option<Stmt>>),
     While(Expr, Box<Stmt>),
+    Throw(Expr),
+    Try(StmtTry),
+    // path of the imported file and the name its namespace is bound to, the
+    // name being at the path when it is the file name
+    Import(Token, Token),
+    Match(Expr, Vec<MatchArm>),
 }
 
-struct Parser<I>
-where
-    I: Iterator<Item = Token>,
-{
-    iter: Peekable<I>,
+impl Expr {
+    // Line of the first token of the expression
+    pub fn line(&self) -> usize {
+        match self {
+            Expr::Leaf(t) | Expr::Assign(t, _) | Expr::Unary(t, _) => t.line,
+            Expr::Binary(_, lhs, _) | Expr::Logical(_, lhs, _) => lhs.line(),
+            Expr::Call(_, callee, _) => callee.line(),
+            Expr::Conditional(expr, ..)
+            | Expr::Grouping(expr)
+            | Expr::Stringify(expr)
+            | Expr::Postfix(_, expr)
+            | Expr::Get(_, expr)
+            | Expr::Set(_, expr, _) => expr.line(),
+        }
+    }
 }
 
-impl<I> Parser<I>
-where
-    I: Iterator<Item = Token>,
-{
+impl Stmt {
+    // Line of the first token of the statement the syntax tree has, None for
+    // empty blocks. Keywords aren't kept, `print x;` is at the line of x
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Stmt::Expression(expr)
+            | Stmt::Print(expr)
+            | Stmt::Return(expr)
+            | Stmt::Throw(expr)
+            | Stmt::If(expr, ..)
+            | Stmt::While(expr, _)
+            | Stmt::Match(expr, _) => Some(expr.line()),
+            Stmt::Var(t, _) | Stmt::Import(t, _) => Some(t.line),
+            Stmt::Function(function) => Some(function.name.line),
+            Stmt::Block(stmts) => stmts.iter().find_map(Stmt::line),
+            Stmt::Try(stmt_try) => stmt_try.body.iter().find_map(Stmt::line),
+        }
+    }
+}
+
+fn is_assignable(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Leaf(Token {
+            token_type: TokenType::Identifier(_),
+            ..
+        }) | Expr::Get(..)
+    )
+}
+
+// Desugar `a += b` into `a = a + b`, where op is the arithmetic operator to apply.
+// For properties this evaluates the object expression twice
+fn compound_assignment(target: Expr, op: Token, rhs: Expr) -> Result<Expr, usize> {
+    let token_type = match op.token_type {
+        TokenType::PlusEqual | TokenType::PlusPlus => TokenType::Plus,
+        TokenType::MinusEqual | TokenType::MinusMinus => TokenType::Minus,
+        TokenType::StarEqual => TokenType::Star,
+        TokenType::SlashEqual => TokenType::Slash,
+        _ => unreachable!(),
+    };
+
+    let value = |target: &Expr| {
+        Box::new(Expr::Binary(
+            Token { token_type, ..op },
+            Box::new(target.clone()),
+            Box::new(rhs),
+        ))
+    };
+    match &target {
+        Expr::Leaf(
+            t @ Token {
+                token_type: TokenType::Identifier(_),
+                ..
+            },
+        ) => Ok(Expr::Assign(t.clone(), value(&target))),
+        Expr::Get(name, object) => Ok(Expr::Set(name.clone(), object.clone(), value(&target))),
+        _ => Err(42),
+    }
+}
+
+// Tokens from start to end, by index, that form a node of the syntax tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeSpan {
+    pub kind: NodeKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+// Deepest nesting of statements and expressions, including chains of operators
+// like `1 + 2 + 3`. Deeper code would overflow the stack of the parser, resolver
+// or interpreter rather than fail
+const MAX_NESTING: usize = 256;
+
+struct Parser {
+    iter: Peekable<vec::IntoIter<Token>>,
+    total: usize,
+    // nodes parsed so far, inner ones before the ones containing them
+    spans: Vec<NodeSpan>,
+    // how deeply nested the code being parsed is
+    depth: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Parser {
+            total: tokens.len(),
+            iter: tokens.into_iter().peekable(),
+            spans: vec![],
+            depth: 0,
+        }
+    }
+
+    // One level deeper, errors leave the depth as is since they end the statement
+    fn nest(&mut self) -> Result<(), usize> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return Err(1150);
+        }
+        Ok(())
+    }
+
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, usize>) -> Result<T, usize> {
+        let depth = self.depth;
+        self.nest()?;
+        let result = parse(self)?;
+        self.depth = depth;
+        Ok(result)
+    }
+
+    // Index of the next token
+    fn pos(&self) -> usize {
+        self.total - self.iter.len()
+    }
+
+    // Record the tokens from start up to here as a node
+    fn mark(&mut self, kind: NodeKind, start: usize) {
+        let end = self.pos();
+        if end > start {
+            self.spans.push(NodeSpan { kind, start, end });
+        }
+    }
+
+    fn node<T>(
+        &mut self,
+        kind: NodeKind,
+        start: usize,
+        parse: impl FnOnce(&mut Self) -> Result<T, usize>,
+    ) -> Result<T, usize> {
+        let result = parse(self)?;
+        self.mark(kind, start);
+        Ok(result)
+    }
+
     fn match_next(&mut self, options: &[TokenType]) -> Option<Token> {
         if let Some(ttype) = self.iter.peek().map(|t| &t.token_type) {
             if options.contains(ttype) {
@@ -67,7 +250,9 @@ where
     }
 
     fn parse_primary(&mut self) -> Result<Expr, usize> {
+        let start = self.pos();
         if let Some(op) = self.match_next(&[TokenType::False, TokenType::True, TokenType::Nil]) {
+            self.mark(NodeKind::Literal, start);
             return Ok(Expr::Leaf(op));
         }
 
@@ -78,37 +263,96 @@ where
                 return Err(1); // Missing closing brace
             }
 
+            self.mark(NodeKind::Grouping, start);
             return Ok(Expr::Grouping(Box::new(expr)));
         }
 
         // Only options left are literals/variables
         if let Some(t) = self.iter.next() {
-            Ok(match t.token_type {
+            let (kind, expr) = match t.token_type.clone() {
+                TokenType::Interpolation(s) => {
+                    (NodeKind::Interpolation, self.parse_interpolation(t, s)?)
+                }
+                TokenType::Identifier(_) => (NodeKind::Variable, Expr::Leaf(t)),
                 TokenType::Number(_)
                 | TokenType::Text(_)
                 | TokenType::True
                 | TokenType::False
-                | TokenType::Nil
-                | TokenType::Identifier(_) => Expr::Leaf(t),
+                | TokenType::Nil => (NodeKind::Literal, Expr::Leaf(t)),
                 _ => return Err(33),
-            })
+            };
+            self.mark(kind, start);
+            Ok(expr)
         } else {
             Err(1010) // Ran out of elements
         }
     }
 
+    // Turn "a ${b} c" into ("a " + b) + " c", with b wrapped in a Stringify.
+    // The scanner yields an Interpolation for each segment followed by an embedded expression,
+    // so after each expression we either see another Interpolation or the closing Text
+    fn parse_interpolation(&mut self, start: Token, segment: String) -> Result<Expr, usize> {
+        let plus = Token {
+            token_type: TokenType::Plus,
+            ..start.clone()
+        };
+        let mut expr = Expr::Leaf(Token {
+            token_type: TokenType::Text(segment),
+            ..start
+        });
+
+        loop {
+            let embedded = self.parse_expression()?;
+            expr = Expr::Binary(
+                plus.clone(),
+                Box::new(expr),
+                Box::new(Expr::Stringify(Box::new(embedded))),
+            );
+
+            let t = self.iter.next().ok_or(1010usize)?;
+            match t.token_type.clone() {
+                TokenType::Interpolation(s) => {
+                    let segment = Expr::Leaf(Token {
+                        token_type: TokenType::Text(s),
+                        ..t
+                    });
+                    expr = Expr::Binary(plus.clone(), Box::new(expr), Box::new(segment));
+                }
+                TokenType::Text(_) => {
+                    return Ok(Expr::Binary(plus, Box::new(expr), Box::new(Expr::Leaf(t))));
+                }
+                _ => return Err(1012), // Unterminated interpolation
+            }
+        }
+    }
+
     fn parse_call(&mut self) -> Result<Expr, usize> {
+        let start = self.pos();
+        let depth = self.depth;
         let mut lhs = self.parse_primary()?;
 
-        while let Some(mut par) = self.match_next(&[TokenType::LeftParen]) {
+        loop {
+            if self.match_next(&[TokenType::Dot]).is_some() {
+                self.nest()?;
+                let (_, name) = self.parse_identifier()?;
+                lhs = Expr::Get(name, Box::new(lhs));
+                self.mark(NodeKind::Get, start);
+                continue;
+            }
+
+            let args_start = self.pos();
+            if self.match_next(&[TokenType::LeftParen]).is_none() {
+                break;
+            }
+            self.nest()?;
+
             let mut args = vec![];
-            if let Some(right_par) = self.match_next(&[TokenType::RightParen]) {
-                par = right_par;
+            let par = if let Some(right_par) = self.match_next(&[TokenType::RightParen]) {
+                right_par
             } else {
                 loop {
                     if args.len() >= 255 {
-                        // todo properly log error here
-                        println!("Too many arguments");
+                        return Err(1052);
                     }
                     args.push(self.parse_expression()?);
                     if self.match_next(&[TokenType::Comma]).is_none() {
@@ -116,46 +360,102 @@ where
                     }
                 }
                 if let Some(right_par) = self.match_next(&[TokenType::RightParen]) {
-                    par = right_par;
+                    right_par
                 } else {
                     return Err(1051);
                 }
-            }
+            };
 
+            self.mark(NodeKind::Arguments, args_start);
             lhs = Expr::Call(par, Box::new(lhs), args);
+            self.mark(NodeKind::Call, start);
         }
 
+        self.depth = depth;
         Ok(lhs)
     }
 
+    fn parse_postfix(&mut self) -> Result<Expr, usize> {
+        let start = self.pos();
+        let expr = self.parse_call()?;
+
+        if let Some(op) = self.match_next(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            if !is_assignable(&expr) {
+                return Err(42);
+            }
+            self.mark(NodeKind::Postfix, start);
+            return Ok(Expr::Postfix(op, Box::new(expr)));
+        }
+
+        Ok(expr)
+    }
+
     fn parse_unary(&mut self) -> Result<Expr, usize> {
+        let start = self.pos();
         if let Some(op) = self.match_next(&[TokenType::Bang, TokenType::Minus]) {
-            let rhs = self.parse_unary()?;
+            let rhs = self.nested(Self::parse_unary)?;
+            self.mark(NodeKind::Unary, start);
             return Ok(Expr::Unary(op, Box::new(rhs)));
         }
 
-        self.parse_call()
+        if let Some(op) = self.match_next(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let rhs = self.nested(Self::parse_unary)?;
+            self.mark(NodeKind::Unary, start);
+            if is_assignable(&rhs) {
+                let one = Expr::Leaf(Token {
+                    token_type: TokenType::Number("1".to_owned()),
+                    ..op.clone()
+                });
+                return compound_assignment(rhs, op, one);
+            }
+
+            // keep `--(3)` a double negation
+            if op.token_type == TokenType::MinusMinus {
+                let minus = Token {
+                    token_type: TokenType::Minus,
+                    ..op
+                };
+                let inner = Expr::Unary(minus.clone(), Box::new(rhs));
+                return Ok(Expr::Unary(minus, Box::new(inner)));
+            }
+
+            return Err(42);
+        }
+
+        self.parse_postfix()
     }
 
     fn parse_multiplication(&mut self) -> Result<Expr, usize> {
+        let start = self.pos();
+        let depth = self.depth;
         let mut lhs = self.parse_unary()?;
         while let Some(op) = self.match_next(&[TokenType::Slash, TokenType::Star]) {
+            self.nest()?;
             let rhs = self.parse_unary()?;
             lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+            self.mark(NodeKind::Binary, start);
         }
+        self.depth = depth;
         Ok(lhs)
     }
 
     fn parse_addition(&mut self) -> Result<Expr, usize> {
+        let start = self.pos();
+        let depth = self.depth;
         let mut lhs = self.parse_multiplication()?;
         while let Some(op) = self.match_next(&[TokenType::Plus, TokenType::Minus]) {
+            self.nest()?;
             let rhs = self.parse_multiplication()?;
             lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+            self.mark(NodeKind::Binary, start);
         }
+        self.depth = depth;
         Ok(lhs)
     }
 
     fn parse_comparison(&mut self) -> Result<Expr, usize> {
+        let start = self.pos();
+        let depth = self.depth;
         let mut lhs = self.parse_addition()?;
         while let Some(op) = self.match_next(&[
             TokenType::Greater,
@@ -163,18 +463,26 @@ where
             TokenType::Less,
             TokenType::LessEqual,
         ]) {
+            self.nest()?;
             let rhs = self.parse_addition()?;
             lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+            self.mark(NodeKind::Binary, start);
         }
+        self.depth = depth;
         Ok(lhs)
     }
 
     fn parse_equality(&mut self) -> Result<Expr, usize> {
+        let start = self.pos();
+        let depth = self.depth;
         let mut lhs = self.parse_comparison()?;
         while let Some(op) = self.match_next(&[TokenType::EqualEqual, TokenType::BangEqual]) {
+            self.nest()?;
             let rhs = self.parse_comparison()?;
             lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+            self.mark(NodeKind::Binary, start);
         }
+        self.depth = depth;
         Ok(lhs)
     }
 
@@ -188,40 +496,86 @@ where
     }
 
     fn parse_logic_and(&mut self) -> Result<Expr, usize> {
+        let start = self.pos();
+        let depth = self.depth;
         let mut expr = self.parse_equality()?;
 
         while let Some(op) = self.match_next(&[TokenType::And]) {
+            self.nest()?;
             let rhs = self.parse_equality()?;
             expr = Expr::Logical(op, Box::new(expr), Box::new(rhs));
+            self.mark(NodeKind::Logical, start);
         }
 
+        self.depth = depth;
         Ok(expr)
     }
 
     fn parse_logic_or(&mut self) -> Result<Expr, usize> {
+        let start = self.pos();
+        let depth = self.depth;
         let mut expr = self.parse_logic_and()?;
 
         while let Some(op) = self.match_next(&[TokenType::Or]) {
+            self.nest()?;
             let rhs = self.parse_logic_and()?;
             expr = Expr::Logical(op, Box::new(expr), Box::new(rhs));
+            self.mark(NodeKind::Logical, start);
         }
 
+        self.depth = depth;
         Ok(expr)
     }
 
+    // Right associative, so `a ? b : c ? d : e` groups as `a ? b : (c ? d : e)`
+    fn parse_conditional(&mut self) -> Result<Expr, usize> {
+        let start = self.pos();
+        let cond = self.parse_logic_or()?;
+
+        if self.match_next(&[TokenType::Question]).is_some() {
+            let then_branch = self.parse_expression()?;
+            if self.match_next(&[TokenType::Colon]).is_none() {
+                return Err(1013); // Missing ':' of conditional
+            }
+            let else_branch = self.nested(Self::parse_conditional)?;
+            self.mark(NodeKind::Conditional, start);
+            return Ok(Expr::Conditional(
+                Box::new(cond),
+                Box::new(then_branch),
+                Box::new(else_branch),
+            ));
+        }
+
+        Ok(cond)
+    }
+
     fn parse_assignment(&mut self) -> Result<Expr, usize> {
-        let expr = self.parse_logic_or()?;
+        let start = self.pos();
+        let expr = self.parse_conditional()?;
 
         if self.match_next(&[TokenType::Equal]).is_some() {
-            let rhs = self.parse_assignment()?;
+            let rhs = self.nested(Self::parse_assignment)?;
+            self.mark(NodeKind::Assign, start);
 
             match expr {
-                Expr::Leaf(Token {
-                    token_type: TokenType::Identifier(s),
-                    ..
-                }) => Ok(Expr::Assign(s, Box::new(rhs))),
+                Expr::Leaf(
+                    t @ Token {
+                        token_type: TokenType::Identifier(_),
+                        ..
+                    },
+                ) => Ok(Expr::Assign(t, Box::new(rhs))),
+                Expr::Get(name, object) => Ok(Expr::Set(name, object, Box::new(rhs))),
                 _ => Err(42),
             }
+        } else if let Some(op) = self.match_next(&[
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+        ]) {
+            let rhs = self.nested(Self::parse_assignment)?;
+            self.mark(NodeKind::Assign, start);
+            compound_assignment(expr, op, rhs)
         } else {
             Ok(expr)
         }
@@ -242,7 +596,7 @@ where
     }
 
     fn parse_expression(&mut self) -> Result<Expr, usize> {
-        self.parse_assignment()
+        self.nested(Self::parse_assignment)
     }
 
     fn parse_exprstmt(&mut self) -> Result<Stmt, usize> {
@@ -332,10 +686,14 @@ where
             Some(self.parse_exprstmt()?)
         };
 
-        let cond = if let Some(sc) = self.match_next(&[TokenType::Semicolon]) {
+        let cond = if let Some(sc) = self
+            .iter
+            .peek()
+            .filter(|t| t.token_type == TokenType::Semicolon)
+        {
             Expr::Leaf(Token {
                 token_type: TokenType::True,
-                ..sc
+                ..sc.clone()
             })
         } else {
             self.parse_expression()?
@@ -370,36 +728,189 @@ where
         Ok(body)
     }
 
+    fn parse_throw(&mut self) -> Result<Stmt, usize> {
+        let expr = self.parse_expression()?;
+        if self.match_next(&[TokenType::Semicolon]).is_none() {
+            Err(1120)
+        } else {
+            Ok(Stmt::Throw(expr))
+        }
+    }
+
+    // Parse a `{ ... }` block and return its statements
+    fn parse_block_body(&mut self) -> Result<Vec<Stmt>, usize> {
+        let start = self.pos();
+        if self.match_next(&[TokenType::LeftBrace]).is_none() {
+            return Err(1121);
+        }
+
+        if let Stmt::Block(stmts) = self.node(NodeKind::Block, start, Self::parse_block)? {
+            Ok(stmts)
+        } else {
+            Err(1073)
+        }
+    }
+
+    fn parse_try(&mut self) -> Result<Stmt, usize> {
+        let body = self.parse_block_body()?;
+
+        let start = self.pos();
+        let catch = if self.match_next(&[TokenType::Catch]).is_some() {
+            if self.match_next(&[TokenType::LeftParen]).is_none() {
+                return Err(1122);
+            }
+            let (_, name) = self.parse_identifier()?;
+            if self.match_next(&[TokenType::RightParen]).is_none() {
+                return Err(1123);
+            }
+            let body = self.parse_block_body()?;
+            self.mark(NodeKind::Catch, start);
+            Some((name, body))
+        } else {
+            None
+        };
+
+        let start = self.pos();
+        let finally = if self.match_next(&[TokenType::Finally]).is_some() {
+            Some(self.node(NodeKind::Finally, start, Self::parse_block_body)?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(1124); // try without catch or finally
+        }
+
+        Ok(Stmt::Try(StmtTry {
+            body,
+            catch,
+            finally,
+        }))
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, usize> {
+        let t = self.iter.next().ok_or(1010usize)?;
+        Ok(match &t.token_type {
+            TokenType::Identifier(_) => Pattern::Binding(t),
+            TokenType::Number(_)
+            | TokenType::Text(_)
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil => Pattern::Literal(Expr::Leaf(t)),
+            TokenType::Minus => match self.iter.next() {
+                Some(
+                    n @ Token {
+                        token_type: TokenType::Number(_),
+                        ..
+                    },
+                ) => Pattern::Literal(Expr::Unary(t, Box::new(Expr::Leaf(n)))),
+                _ => return Err(1141),
+            },
+            _ => return Err(1141), // Not a valid pattern
+        })
+    }
+
+    // `case <pattern>, ... [if <guard>] => <stmt>` or `default => <stmt>`
+    fn parse_match_arm(&mut self) -> Result<MatchArm, usize> {
+        let mut patterns = vec![];
+        let mut guard = None;
+
+        if self.match_next(&[TokenType::Default]).is_some() {
+            patterns.push(Pattern::Default);
+        } else if self.match_next(&[TokenType::Case]).is_some() {
+            loop {
+                patterns.push(self.parse_pattern()?);
+                if self.match_next(&[TokenType::Comma]).is_none() {
+                    break;
+                }
+            }
+            if self.match_next(&[TokenType::If]).is_some() {
+                guard = Some(self.parse_expression()?);
+            }
+        } else {
+            return Err(1142); // Expect 'case' or 'default'
+        }
+
+        if self.match_next(&[TokenType::FatArrow]).is_none() {
+            return Err(1143);
+        }
+
+        let body = self.parse_stmt()?;
+        Ok(MatchArm {
+            patterns,
+            guard,
+            body,
+        })
+    }
+
+    fn parse_match(&mut self) -> Result<Stmt, usize> {
+        if self.match_next(&[TokenType::LeftParen]).is_none() {
+            return Err(1140);
+        }
+        let subject = self.parse_expression()?;
+        if self.match_next(&[TokenType::RightParen]).is_none() {
+            return Err(1140);
+        }
+
+        if self.match_next(&[TokenType::LeftBrace]).is_none() {
+            return Err(1144);
+        }
+        let mut arms = vec![];
+        while self.match_next(&[TokenType::RightBrace]).is_none() {
+            let start = self.pos();
+            arms.push(self.node(NodeKind::MatchArm, start, Self::parse_match_arm)?);
+        }
+
+        Ok(Stmt::Match(subject, arms))
+    }
+
     fn parse_stmt(&mut self) -> Result<Stmt, usize> {
+        self.nested(Self::parse_nested_stmt)
+    }
+
+    fn parse_nested_stmt(&mut self) -> Result<Stmt, usize> {
+        let start = self.pos();
         if self.match_next(&[TokenType::Return]).is_some() {
-            return self.parse_return();
+            return self.node(NodeKind::Return, start, Self::parse_return);
+        }
+
+        if self.match_next(&[TokenType::Throw]).is_some() {
+            return self.node(NodeKind::Throw, start, Self::parse_throw);
+        }
+
+        if self.match_next(&[TokenType::Try]).is_some() {
+            return self.node(NodeKind::Try, start, Self::parse_try);
+        }
+
+        if self.match_next(&[TokenType::Match]).is_some() {
+            return self.node(NodeKind::Match, start, Self::parse_match);
         }
 
         if self.match_next(&[TokenType::For]).is_some() {
-            return self.parse_for();
+            return self.node(NodeKind::For, start, Self::parse_for);
         }
 
         if self.match_next(&[TokenType::If]).is_some() {
-            return self.parse_if();
+            return self.node(NodeKind::If, start, Self::parse_if);
         }
 
         if self.match_next(&[TokenType::Print]).is_some() {
-            return self.parse_print();
+            return self.node(NodeKind::Print, start, Self::parse_print);
         }
 
         if self.match_next(&[TokenType::While]).is_some() {
-            return self.parse_while();
+            return self.node(NodeKind::While, start, Self::parse_while);
         }
 
         if self.match_next(&[TokenType::LeftBrace]).is_some() {
-            return self.parse_block();
+            return self.node(NodeKind::Block, start, Self::parse_block);
         }
 
-        self.parse_exprstmt()
+        self.node(NodeKind::ExprStmt, start, Self::parse_exprstmt)
     }
 
     fn parse_vardecl(&mut self) -> Result<Stmt, usize> {
-        let (s, t) = self.parse_identifier()?;
+        let (_, t) = self.parse_identifier()?;
         let expr = if self.match_next(&[TokenType::Equal]).is_some() {
             self.parse_expression()?
         } else {
@@ -411,12 +922,13 @@ where
         if self.match_next(&[TokenType::Semicolon]).is_none() {
             Err(44)
         } else {
-            Ok(Stmt::Var(s, expr))
+            Ok(Stmt::Var(t, expr))
         }
     }
 
     fn parse_fun(&mut self) -> Result<Stmt, usize> {
-        let (name, _) = self.parse_identifier()?;
+        let (_, name) = self.parse_identifier()?;
+        let start = self.pos();
         if self.match_next(&[TokenType::LeftParen]).is_none() {
             return Err(1071);
         }
@@ -424,7 +936,7 @@ where
         let mut params = vec![];
         if self.match_next(&[TokenType::RightParen]).is_none() {
             loop {
-                params.push(self.parse_identifier()?.0);
+                params.push(self.parse_identifier()?.1);
                 if self.match_next(&[TokenType::Comma]).is_none() {
                     break;
                 }
@@ -435,28 +947,67 @@ where
         }
 
         if params.len() >= 255 {
-            // todo proper logging
-            println!("function has too many arguments!");
+            return Err(1076);
         }
+        self.mark(NodeKind::Parameters, start);
 
         // Consume { as parse_block requires it
+        let start = self.pos();
         if self.match_next(&[TokenType::LeftBrace]).is_none() {
             return Err(1075);
         }
 
-        if let Stmt::Block(body) = self.parse_block()? {
-            return Ok(Stmt::Function(StmtFunction { name, params, body }));
+        if let Stmt::Block(body) = self.node(NodeKind::Block, start, Self::parse_block)? {
+            Ok(Stmt::Function(StmtFunction { name, params, body }))
+        } else {
+            Err(1073)
+        }
+    }
+
+    // `import "path";` binds the namespace to the file name without extension,
+    // `import "path" as name;` to the given name. "as" is not reserved outside of imports
+    fn parse_import(&mut self) -> Result<Stmt, usize> {
+        let (path, stem) = match self.iter.next() {
+            Some(t) => match &t.token_type {
+                TokenType::Text(s) => {
+                    let stem = Path::new(s).file_stem().and_then(|stem| stem.to_str());
+                    let stem = Token {
+                        token_type: TokenType::Identifier(stem.unwrap_or_default().to_owned()),
+                        ..t.clone()
+                    };
+                    (t, stem)
+                }
+                _ => return Err(1130),
+            },
+            None => return Err(1130),
+        };
+
+        let name = if self.iter.peek().map(|t| &t.token_type)
+            == Some(&TokenType::Identifier("as".to_owned()))
+        {
+            self.iter.next();
+            self.parse_identifier()?.1
         } else {
-            return Err(1073);
+            stem
+        };
+
+        if self.match_next(&[TokenType::Semicolon]).is_none() {
+            Err(1131)
+        } else {
+            Ok(Stmt::Import(path, name))
         }
     }
 
     fn parse_decl(&mut self) -> Result<Stmt, usize> {
-        let next_token = self.match_next(&[TokenType::Var, TokenType::Fun]);
+        let start = self.pos();
+        let next_token = self.match_next(&[TokenType::Var, TokenType::Fun, TokenType::Import]);
         if let Some(token) = next_token {
             match token.token_type {
-                TokenType::Fun => self.parse_fun(),
-                TokenType::Var => self.parse_vardecl(),
+                TokenType::Fun => {
+                    self.nested(|p| p.node(NodeKind::Function, start, Self::parse_fun))
+                }
+                TokenType::Var => self.node(NodeKind::Var, start, Self::parse_vardecl),
+                TokenType::Import => self.node(NodeKind::Import, start, Self::parse_import),
                 _ => unreachable!(),
             }
         } else {
@@ -465,23 +1016,97 @@ where
     }
 }
 
-pub fn parse<'a, I>(tokens: I) -> Vec<Stmt>
+// Message of the syntax error codes
+fn describe(code: usize) -> &'static str {
+    match code {
+        1 => "Expect ')' after expression.",
+        22 => "Expect ';' after statement.",
+        33 | 1010 => "Expect expression.",
+        42 => "Invalid assignment target.",
+        44 => "Expect ';' after variable declaration.",
+        51 => "Expect parentheses around condition.",
+        61 => "Expect '(' after 'for'.",
+        62 => "Expect ';' after loop condition.",
+        63 => "Expect ')' after for clauses.",
+        1009 => "Expect identifier.",
+        1011 => "Expect end of expression.",
+        1012 => "Unterminated string interpolation.",
+        1013 => "Expect ':' after then branch of conditional expression.",
+        1051 => "Expect ')' after arguments.",
+        1052 => "Can't have more than 255 arguments.",
+        1071 => "Expect '(' after function name.",
+        1074 => "Expect ')' after parameters.",
+        1075 => "Expect '{' before function body.",
+        1076 => "Can't have more than 255 parameters.",
+        1110 => "Expect ';' after return value.",
+        1120 => "Expect ';' after thrown value.",
+        1121 => "Expect '{' before block.",
+        1122 => "Expect '(' after 'catch'.",
+        1123 => "Expect ')' after catch variable.",
+        1124 => "Expect 'catch' or 'finally' after try block.",
+        1130 => "Expect path string after 'import'.",
+        1131 => "Expect ';' after import.",
+        1140 => "Expect parentheses around match subject.",
+        1141 => "Expect literal, name or 'default' as pattern.",
+        1142 => "Expect 'case' or 'default'.",
+        1143 => "Expect '=>' after match pattern.",
+        1144 => "Expect '{' before match arms.",
+        1150 => "Code is nested too deeply.",
+        _ => "Invalid syntax.",
+    }
+}
+
+fn syntax_error(code: usize, at: Option<&Token>, last_line: usize) -> Diagnostic {
+    Diagnostic {
+        line: at.map_or(last_line, |t| t.line),
+        message: describe(code).to_owned(),
+    }
+}
+
+// Statements of the program, the problems of the parts that couldn't be parsed
+// and the nodes of the syntax tree the tokens form, the tokens of statements
+// that didn't parse forming Error nodes
+pub fn parse_spans<I>(tokens: I) -> (Vec<Stmt>, Vec<Diagnostic>, Vec<NodeSpan>)
 where
     I: IntoIterator<Item = Token>,
 {
-    let mut parser = Parser {
-        iter: tokens.into_iter().peekable(),
-    };
+    let tokens: Vec<Token> = tokens.into_iter().collect();
+    let last_line = tokens.last().map_or(1, |t| t.line);
+    let mut parser = Parser::new(tokens);
     let mut res = vec![];
-    while !parser.iter.peek().is_none() {
+    let mut errors = vec![];
+    while parser.iter.peek().is_some() {
+        let start = parser.pos();
         match parser.parse_decl() {
             Ok(stmt) => res.push(stmt),
             Err(e) => {
-                println!("Parser error {}!", e);
+                parser.depth = 0;
+                errors.push(syntax_error(e, parser.iter.peek(), last_line));
                 parser.iter.next(); // skip unparsable token
+                // going on would hit the same nesting again and again
+                if e == 1150 {
+                    parser.iter.by_ref().for_each(drop);
+                }
+                parser.mark(NodeKind::Error, start);
             }
         }
+    }
+    (res, errors, parser.spans)
+}
 
+// A lone expression, e.g. for evaluating it on the fly, which must use up all tokens
+pub fn parse_expression<I>(tokens: I) -> Result<Expr, Diagnostic>
+where
+    I: IntoIterator<Item = Token>,
+{
+    let tokens: Vec<Token> = tokens.into_iter().collect();
+    let last_line = tokens.last().map_or(1, |t| t.line);
+    let mut parser = Parser::new(tokens);
+    let expr = parser
+        .parse_expression()
+        .map_err(|e| syntax_error(e, parser.iter.peek(), last_line))?;
+    match parser.iter.peek() {
+        Some(t) => Err(syntax_error(1011, Some(t), last_line)),
+        None => Ok(expr),
     }
-    res
 }
@@ -0,0 +1,112 @@
+use std::path::{Component, Path, PathBuf};
+
+// Files a script may access through natives
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Access {
+    #[default]
+    Denied,
+    All,
+    // these files and everything below these directories
+    Paths(Vec<PathBuf>),
+}
+
+impl Access {
+    fn permits(&self, path: &Path) -> bool {
+        match self {
+            Access::Denied => false,
+            Access::All => true,
+            Access::Paths(allowed) => {
+                let path = resolve(path);
+                allowed
+                    .iter()
+                    .any(|allowed| path.starts_with(resolve(allowed)))
+            }
+        }
+    }
+}
+
+// Absolute path with symlinks resolved where the file exists, so `..` or a link
+// can't lead out of an allowed directory
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    // a file about to be created, its directory exists though
+    if let (Some(parent), Some(name)) = (path.parent(), path.file_name()) {
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        if let Ok(parent) = parent.canonicalize() {
+            return parent.join(name);
+        }
+    }
+
+    let mut resolved = std::env::current_dir().unwrap_or_default();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            component => resolved.push(component),
+        }
+    }
+    resolved
+}
+
+// What natives may do on behalf of a script, nothing unless granted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Permissions {
+    pub read: Access,
+    pub write: Access,
+    // reading environment variables
+    pub env: bool,
+    // reading the time of day
+    pub clock: bool,
+}
+
+impl Permissions {
+    // For trusted scripts
+    pub fn all() -> Self {
+        Permissions {
+            read: Access::All,
+            write: Access::All,
+            env: true,
+            clock: true,
+        }
+    }
+
+    pub fn check_read(&self, path: &str) -> Result<(), String> {
+        if self.read.permits(Path::new(path)) {
+            Ok(())
+        } else {
+            Err(format!("Permission denied: no read access to '{}'.", path))
+        }
+    }
+
+    pub fn check_write(&self, path: &str) -> Result<(), String> {
+        if self.write.permits(Path::new(path)) {
+            Ok(())
+        } else {
+            Err(format!("Permission denied: no write access to '{}'.", path))
+        }
+    }
+
+    pub fn check_env(&self) -> Result<(), String> {
+        if self.env {
+            Ok(())
+        } else {
+            Err("Permission denied: no environment access.".to_owned())
+        }
+    }
+
+    pub fn check_clock(&self) -> Result<(), String> {
+        if self.clock {
+            Ok(())
+        } else {
+            Err("Permission denied: no clock access.".to_owned())
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Diagnostic;
+use crate::value::Er;
+
+// A nondeterministic input read by a run
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    // milliseconds returned by clock()
+    Clock(f64),
+    // seed of random() and randomInt() when the script didn't call seed()
+    Seed(u64),
+    // line returned by readLine(), None at the end of input
+    Line(Option<String>),
+    // value of an environment variable, None if it isn't set
+    Env(String, Option<String>),
+}
+
+impl Event {
+    // What was read, for divergence errors
+    fn kind(&self) -> &'static str {
+        match self {
+            Event::Clock(_) => "the clock",
+            Event::Seed(_) => "a random seed",
+            Event::Line(_) => "an input line",
+            Event::Env(..) => "an environment variable",
+        }
+    }
+}
+
+// Events in the order a run read them, one per line in the text form, where
+// `#` starts a comment:
+//
+//     clock 1700000000000
+//     seed 42
+//     line "some input"
+//     line none
+//     env HOME "/home/me"
+//     env EDITOR none
+//
+// Clones share the events, so a trace handed to a session can be read afterwards
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    events: Rc<RefCell<VecDeque<Event>>>,
+    // why a replay of the trace diverged, until the run is stopped for it
+    diverged: Rc<RefCell<Option<String>>>,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.events.borrow().iter().cloned().collect()
+    }
+
+    pub fn push(&self, event: Event) {
+        self.events.borrow_mut().push_back(event);
+    }
+
+    fn pop(&self) -> Option<Event> {
+        self.events.borrow_mut().pop_front()
+    }
+
+    // Read the text form, which may also be written by hand to script the inputs
+    pub fn parse(text: &str) -> Result<Trace, Vec<Diagnostic>> {
+        let trace = Trace::new();
+        let mut errors = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_event(line) {
+                Ok(event) => trace.push(event),
+                Err(message) => errors.push(Diagnostic {
+                    line: i + 1,
+                    message,
+                }),
+            }
+        }
+        if errors.is_empty() {
+            Ok(trace)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in self.events.borrow().iter() {
+            match event {
+                Event::Clock(ms) => writeln!(f, "clock {}", ms)?,
+                Event::Seed(seed) => writeln!(f, "seed {}", seed)?,
+                Event::Line(line) => writeln!(f, "line {}", quote(line))?,
+                Event::Env(name, value) => writeln!(f, "env {} {}", name, quote(value))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Optional text of an event, `none` standing for the end of input or an unset variable
+fn quote(text: &Option<String>) -> String {
+    let text = match text {
+        Some(text) => text,
+        None => return "none".to_owned(),
+    };
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn unquote(text: &str) -> Result<Option<String>, String> {
+    if text == "none" {
+        return Ok(None);
+    }
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .filter(|_| text.len() >= 2)
+        .ok_or_else(|| format!("Expected a quoted string or 'none' but got '{}'.", text))?;
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('"') => unquoted.push('"'),
+            Some('\\') => unquoted.push('\\'),
+            Some('n') => unquoted.push('\n'),
+            Some('r') => unquoted.push('\r'),
+            Some('t') => unquoted.push('\t'),
+            Some(c) => return Err(format!("Unknown escape '\\{}'.", c)),
+            None => return Err("Unterminated escape.".to_owned()),
+        }
+    }
+    Ok(Some(unquoted))
+}
+
+fn parse_event(line: &str) -> Result<Event, String> {
+    let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let rest = rest.trim();
+    let number = |what: &str| format!("Expected {} but got '{}'.", what, rest);
+    match kind {
+        "clock" => rest
+            .parse()
+            .map(Event::Clock)
+            .map_err(|_| number("a number")),
+        "seed" => rest
+            .parse()
+            .map(Event::Seed)
+            .map_err(|_| number("an unsigned integer")),
+        "line" => unquote(rest).map(Event::Line),
+        "env" => {
+            let (name, value) = rest
+                .split_once(' ')
+                .ok_or_else(|| "Expected a name and a value.".to_owned())?;
+            Ok(Event::Env(name.to_owned(), unquote(value.trim())?))
+        }
+        _ => Err(format!("Unknown event '{}'.", kind)),
+    }
+}
+
+// Where clock(), random(), readLine() and getEnv() get their values from
+#[derive(Debug, Clone, Default)]
+pub enum Sources {
+    // the system clock and environment, the session's input
+    #[default]
+    Live,
+    // live, and everything read is appended to the trace
+    Record(Trace),
+    // everything is taken from the trace in order, a run reading something
+    // else than what was recorded is stopped with an error Lox can't catch
+    Replay(Trace),
+}
+
+impl Sources {
+    // Fails the native reading, and has the interpreter stop the run once it returns
+    fn diverged(&self, message: String) -> String {
+        if let Sources::Replay(trace) = self {
+            *trace.diverged.borrow_mut() = Some(message.clone());
+        }
+        message
+    }
+
+    fn mismatch(&self, read: &str, recorded: &Event) -> String {
+        self.diverged(format!(
+            "Replay diverged: read {} but the trace has {} next.",
+            read,
+            recorded.kind()
+        ))
+    }
+
+    pub(crate) fn check_diverged(&self) -> Result<(), Er> {
+        match self {
+            Sources::Replay(trace) => match trace.diverged.take() {
+                Some(message) => Err(Er::Diverged(message)),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn next(&self, live: impl FnOnce() -> Result<Event, String>) -> Result<Event, String> {
+        match self {
+            Sources::Live => live(),
+            Sources::Record(trace) => {
+                let event = live()?;
+                trace.push(event.clone());
+                Ok(event)
+            }
+            Sources::Replay(trace) => trace
+                .pop()
+                .ok_or_else(|| self.diverged("Replay diverged: the trace has ended.".to_owned())),
+        }
+    }
+
+    pub fn clock(&self) -> Result<f64, String> {
+        match self.next(|| Ok(Event::Clock(since_epoch().as_millis() as f64)))? {
+            Event::Clock(ms) => Ok(ms),
+            event => Err(self.mismatch("the clock", &event)),
+        }
+    }
+
+    pub fn seed(&self) -> Result<u64, String> {
+        match self.next(|| Ok(Event::Seed(since_epoch().as_nanos() as u64)))? {
+            Event::Seed(seed) => Ok(seed),
+            event => Err(self.mismatch("a random seed", &event)),
+        }
+    }
+
+    // read_line reads from the session's input when the line isn't replayed
+    pub fn line(
+        &self,
+        read_line: impl FnOnce() -> Result<Option<String>, String>,
+    ) -> Result<Option<String>, String> {
+        match self.next(|| read_line().map(Event::Line))? {
+            Event::Line(line) => Ok(line),
+            event => Err(self.mismatch("an input line", &event)),
+        }
+    }
+
+    pub fn env(&self, name: &str) -> Result<Option<String>, String> {
+        let live = || Ok(Event::Env(name.to_owned(), std::env::var(name).ok()));
+        match self.next(live)? {
+            Event::Env(recorded, value) if recorded == name => Ok(value),
+            Event::Env(recorded, _) => Err(self.diverged(format!(
+                "Replay diverged: read environment variable '{}' but '{}' was recorded.",
+                name, recorded
+            ))),
+            event => Err(self.mismatch("an environment variable", &event)),
+        }
+    }
+}
+
+fn since_epoch() -> std::time::Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
@@ -1,32 +1,206 @@
+use std::collections::HashMap;
+
 use crate::interpreter::Interpreter;
 use crate::environment::EnvStack;
-use crate::parser::{ Stmt, Expr, StmtFunction };
-use crate::value::{ Value, Er };
+use crate::error::Diagnostic;
+use crate::parser::{ Stmt, Expr, StmtFunction, StmtTry, MatchArm, Pattern };
 use crate::token::{ Token, TokenType };
 
+// Line and column of a token
+type Position = (usize, usize);
+
+fn position(token: &Token) -> Position {
+    (token.line, token.col)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    // with the names of its parameters
+    Function(Vec<String>),
+    Parameter,
+    // the variable of a catch clause
+    Exception,
+    // a name bound by a match pattern
+    Binding,
+    // the namespace of an import
+    Module,
+}
+
+impl SymbolKind {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            SymbolKind::Variable => "variable",
+            SymbolKind::Function(_) => "function",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Exception => "caught exception",
+            SymbolKind::Binding => "match binding",
+            SymbolKind::Module => "module",
+        }
+    }
+}
+
+// A declared name and the places it is used
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    // the name where it is declared
+    pub token: Token,
+    // None on the top level
+    pub scope: Option<usize>,
+    // in the order of the source
+    pub references: Vec<Token>,
+}
+
+impl Symbol {
+    pub fn name(&self) -> &str {
+        self.token.name()
+    }
+}
+
+// The tokens of a block, function or other construct with its own variables
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    pub parent: Option<usize>,
+    pub start: Option<Position>,
+    // the first token after the scope, None if the source ends first
+    pub end: Option<Position>,
+    last: Option<Position>,
+}
+
+// What the resolver learned about the names of a program, for tools
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    pub symbols: Vec<Symbol>,
+    pub scopes: Vec<Scope>,
+}
+
+impl Symbols {
+    // The symbol declared or used at the position
+    pub fn at(&self, line: usize, col: usize) -> Option<&Symbol> {
+        // right after the name too, where the cursor is when typing it
+        let covers = |token: &Token| {
+            let end = token.col + token.name().chars().count();
+            token.line == line && (token.col..=end).contains(&col)
+        };
+        self.symbols
+            .iter()
+            .find(|symbol| covers(&symbol.token) || symbol.references.iter().any(covers))
+    }
+
+    // The symbol a token declares or uses
+    pub fn of(&self, token: &Token) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .find(|symbol| symbol.token == *token || symbol.references.contains(token))
+    }
+
+    // The symbols that can be used at the position, innermost first. Globals can
+    // be used anywhere, as functions may refer to those declared after them
+    pub fn in_scope(&self, line: usize, col: usize) -> Vec<&Symbol> {
+        let at = (line, col);
+        let innermost = (0..self.scopes.len()).rev().find(|&i| {
+            let scope = &self.scopes[i];
+            scope.start.is_some_and(|start| start <= at) && scope.end.is_none_or(|end| at < end)
+        });
+
+        let mut scopes = vec![];
+        let mut scope = innermost;
+        while let Some(i) = scope {
+            scopes.push(i);
+            scope = self.scopes[i].parent;
+        }
+
+        let mut visible = vec![];
+        for i in scopes {
+            visible.extend(self.symbols.iter().rev().filter(|symbol| {
+                symbol.scope == Some(i) && position(&symbol.token) < at
+            }));
+        }
+        visible.extend(self.symbols.iter().filter(|symbol| symbol.scope.is_none()));
+        visible
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Resolver {
     interpreter: Interpreter,
-    env: EnvStack::<bool>,
+    // whether each local is defined yet, and its symbol
+    env: EnvStack::<(bool, usize)>,
+    // line of the last token seen, for the diagnostics
+    line: usize,
+    pub errors: Vec<Diagnostic>,
+    symbols: Symbols,
+    // the first top level declaration of each name
+    globals: HashMap<String, usize>,
+    // uses of names that aren't local, resolved against the globals at the end
+    unresolved: Vec<Token>,
+    // scopes being resolved, innermost last, and those ended with no token seen after them yet
+    open: Vec<usize>,
+    closing: Vec<usize>,
 }
 
 impl Resolver {
-    fn resolve_block(&mut self, stmts: &Vec<Stmt>) {
+    fn error(&mut self, message: &str) {
+        self.errors.push(Diagnostic {
+            line: self.line,
+            message: message.to_owned(),
+        });
+    }
+
+    fn begin_scope(&mut self) {
         self.env.push_default();
+        self.symbols.scopes.push(Scope {
+            parent: self.open.last().copied(),
+            ..Default::default()
+        });
+        self.open.push(self.symbols.scopes.len() - 1);
+    }
+
+    fn end_scope(&mut self) {
+        self.env.pop().unwrap();
+        self.closing.extend(self.open.pop());
+    }
+
+    // Extend the open scopes to the token, and end the closed ones before it
+    fn seen(&mut self, token: &Token) {
+        let at = position(token);
+        for &i in &self.open {
+            let scope = &mut self.symbols.scopes[i];
+            scope.start = Some(scope.start.map_or(at, |start| start.min(at)));
+            scope.last = Some(scope.last.map_or(at, |last| last.max(at)));
+        }
+        // desugared loops visit their tokens out of order
+        let scopes = &mut self.symbols.scopes;
+        self.closing.retain(|&i| {
+            let after = scopes[i].last.is_none_or(|last| last < at);
+            if after {
+                scopes[i].end = Some(at);
+            }
+            !after
+        });
+    }
+
+    fn resolve_block(&mut self, stmts: &Vec<Stmt>) {
+        self.begin_scope();
         for stmt in stmts {
             self.resolve_stmt(stmt);
         }
-        self.env.pop().unwrap();
+        self.end_scope();
     }
 
-    fn resolve_fun(&mut self, stmtFun: &StmtFunction) {
-        self.env.push_default();
-        for param in &stmtFun.params {
-            self.declare(param);
+    fn resolve_fun(&mut self, stmt_fun: &StmtFunction) {
+        let params = stmt_fun.params.iter().map(|param| param.name().to_owned());
+        self.declare(&stmt_fun.name, SymbolKind::Function(params.collect()));
+        self.define(&stmt_fun.name);
+
+        self.begin_scope();
+        for param in &stmt_fun.params {
+            self.declare(param, SymbolKind::Parameter);
             self.define(param);
         }
-        self.resolve_block(&stmtFun.body);
-        self.env.pop().unwrap();
+        self.resolve_block(&stmt_fun.body);
+        self.end_scope();
     }
 
     fn resolve_if(&mut self, cond: &Expr, then: &Stmt, els: &Option<Stmt>) {
@@ -38,65 +212,151 @@ impl Resolver {
     }
 
     // Return the resolved depth, or None if global
-    fn resolve_local(&mut self, name: &str) -> Option<usize> {
-        if self.env.get(name).ok().copied() == Some(false) {
-            // todo proper logging
-            println!("Cannot read local variable in its own initializer");
+    fn resolve_local(&mut self, token: &Token) -> Option<usize> {
+        self.seen(token);
+        let name = token.name();
+        match self.env.get(name).ok().copied() {
+            Some((defined, symbol)) => {
+                if !defined {
+                    self.error("Can't read local variable in its own initializer.");
+                }
+                self.symbols.symbols[symbol].references.push(token.clone());
+            }
+            None => self.unresolved.push(token.clone()),
         }
 
         self.env.resolve_depth(name)
     }
 
-    fn resolve_expr(&mut self, expr: &Expr) {
+    pub fn resolve_expr(&mut self, expr: &Expr) {
         match expr {
-            Expr::Assign(name, exp) => {
+            Expr::Assign(t, exp) => {
                 self.resolve_expr(exp);
-                if self.env.get(name).ok().copied() == Some(false) {
-                    // todo proper logging
-                    println!("Cannot read local variable in its own initializer");
-                }
-
-                if let Some(depth) = self.resolve_local(name) {
+                if let Some(depth) = self.resolve_local(t) {
                     self.interpreter.resolve(exp, depth)
                 };
             }
-            Expr::Binary(_, l, r) | Expr::Logical(_, l, r) => {
+            Expr::Binary(t, l, r) | Expr::Logical(t, l, r) => {
+                self.line = t.line;
+                self.seen(t);
                 self.resolve_expr(l);
                 self.resolve_expr(r);
             }
-            Expr::Call(_, callee, args) => {
+            Expr::Call(t, callee, args) => {
+                self.line = t.line;
+                self.seen(t);
                 self.resolve_expr(callee);
                 for expr in args {
                     self.resolve_expr(expr);
                 }
             }
-            Expr::Grouping(expr) | Expr::Unary(_, expr) => {
+            Expr::Set(t, object, value) => {
+                self.line = t.line;
+                self.seen(t);
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            }
+            Expr::Conditional(cond, l, r) => {
+                self.resolve_expr(cond);
+                self.resolve_expr(l);
+                self.resolve_expr(r);
+            }
+            Expr::Grouping(expr) | Expr::Stringify(expr) => {
+                self.resolve_expr(expr);
+            }
+            Expr::Unary(t, expr) | Expr::Postfix(t, expr) | Expr::Get(t, expr) => {
+                self.seen(t);
                 self.resolve_expr(expr);
             }
             Expr::Leaf(t) => {
-                if let Token { token_type: TokenType::Identifier(name), .. } = t {
-                    println!("t: {:?}", t);
-                    if let Some(depth) = self.resolve_local(name) {
+                self.line = t.line;
+                if let Token { token_type: TokenType::Identifier(_), .. } = t {
+                    if let Some(depth) = self.resolve_local(t) {
                         self.interpreter.resolve(expr, depth)
                     };
+                } else {
+                    self.seen(t);
                 }
             },
         }
     }
 
-    // note that declarations on the top level noop for the resolver
-    fn declare(&mut self, name: &String) {
-        self.env.define(name, false);
+    // note that declarations on the top level are only recorded as symbols,
+    // globals being looked up when the program runs
+    fn declare(&mut self, token: &Token, kind: SymbolKind) {
+        self.seen(token);
+        let symbol = self.symbols.symbols.len();
+        self.symbols.symbols.push(Symbol {
+            kind,
+            token: token.clone(),
+            scope: self.open.last().copied(),
+            references: vec![],
+        });
+        if self.open.is_empty() {
+            self.globals.entry(token.name().to_owned()).or_insert(symbol);
+        }
+        self.env.define(token.name(), (false, symbol));
     }
 
-    fn define(&mut self, name: &String) {
-        self.env.assign(name, true).ok();
+    fn define(&mut self, token: &Token) {
+        if let Ok(&(_, symbol)) = self.env.get(token.name()) {
+            self.env.assign(token.name(), (true, symbol)).ok();
+        }
     }
 
-    fn resolve_var(&mut self, name: &String, expr: &Expr) {
-        self.declare(name);
+    fn resolve_var(&mut self, token: &Token, expr: &Expr) {
+        self.declare(token, SymbolKind::Variable);
         self.resolve_expr(expr);
-        self.define(name);
+        self.define(token);
+    }
+
+    fn resolve_try(&mut self, stmt_try: &StmtTry) {
+        self.resolve_block(&stmt_try.body);
+        if let Some((name, handler)) = &stmt_try.catch {
+            self.begin_scope();
+            self.declare(name, SymbolKind::Exception);
+            self.define(name);
+            self.resolve_block(handler);
+            self.end_scope();
+        }
+        if let Some(finally) = &stmt_try.finally {
+            self.resolve_block(finally);
+        }
+    }
+
+    fn resolve_match(&mut self, subject: &Expr, arms: &[MatchArm]) {
+        self.resolve_expr(subject);
+
+        let mut exhausted = false;
+        for arm in arms {
+            self.begin_scope();
+            for pattern in &arm.patterns {
+                match pattern {
+                    Pattern::Binding(name) => {
+                        self.declare(name, SymbolKind::Binding);
+                        self.define(name);
+                    }
+                    Pattern::Literal(expr) => self.resolve_expr(expr),
+                    Pattern::Default => {}
+                }
+            }
+            // checked after the patterns, so the line is the arm's
+            if exhausted {
+                self.error("Unreachable match arm after a default or catch-all case.");
+            }
+            if let Some(guard) = &arm.guard {
+                self.resolve_expr(guard);
+            }
+            self.resolve_stmt(&arm.body);
+            self.end_scope();
+
+            // a default or an unguarded binding matches everything
+            let catch_all = arm
+                .patterns
+                .iter()
+                .any(|p| matches!(p, Pattern::Default | Pattern::Binding(_)));
+            exhausted |= catch_all && arm.guard.is_none();
+        }
     }
 
     fn resolve_while(&mut self, cond: &Expr, body: &Stmt) {
@@ -108,13 +368,37 @@ impl Resolver {
         match stmt {
             Stmt::Block(stmts) => self.resolve_block(stmts),
             Stmt::Function(stmt_fun) => self.resolve_fun(stmt_fun),
-            Stmt::If(cond, then, els) => self.resolve_if(cond, &*then, &*els),
+            Stmt::If(cond, then, els) => self.resolve_if(cond, then, els),
             | Stmt::Expression(expr)
             | Stmt::Print(expr)
-            | Stmt::Return(expr) => self.resolve_expr(expr),
+            | Stmt::Return(expr)
+            | Stmt::Throw(expr) => self.resolve_expr(expr),
             Stmt::Var(name, expr) => self.resolve_var(name, expr),
             Stmt::While(cond, body) => self.resolve_while(cond, body),
-            _ => unimplemented!(),
+            Stmt::Try(stmt_try) => self.resolve_try(stmt_try),
+            Stmt::Match(subject, arms) => self.resolve_match(subject, arms),
+            Stmt::Import(t, name) => {
+                self.line = t.line;
+                self.seen(t);
+                self.declare(name, SymbolKind::Module);
+                self.define(name);
+            }
+        }
+    }
+
+    // The symbols of everything resolved, with the remaining uses bound to the
+    // globals of their name. Uses of undeclared globals, like the natives, are left out
+    pub fn symbols(mut self) -> Symbols {
+        for token in std::mem::take(&mut self.unresolved) {
+            if let Some(&symbol) = self.globals.get(token.name()) {
+                self.symbols.symbols[symbol].references.push(token);
+            }
+        }
+        for symbol in &mut self.symbols.symbols {
+            symbol.references.sort_by_key(position);
+            // `a += 1` uses the name both as an operand and as the target
+            symbol.references.dedup();
         }
+        self.symbols
     }
 }
\ No newline at end of file
@@ -1,36 +1,134 @@
+use crate::error::Diagnostic;
 use crate::token::*;
 
-use std::iter::Peekable;
 use std::str::Chars;
 
-extern crate itertools;
+fn is_digit(c: char) -> bool {
+    c.is_ascii_digit()
+}
 
-#[derive(Debug, Clone)]
-pub struct ScanState<'a> {
-    chars: Peekable<Chars<'a>>,
-    tokens: Vec<Token>,
-    start: usize,
-    current: usize,
+fn is_alpha(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+// Position in the source, keeping track of the line it is on
+struct Cursor<'a> {
+    source: &'a str,
+    offset: usize,
     line: usize,
+    // offset of the first character of the line
+    line_start: usize,
 }
 
-fn is_digit(c: char) -> bool {
-    c >= '0' && c <= '9'
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.source[self.offset..].chars().nth(1)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.line_start = self.offset;
+        }
+        Some(c)
+    }
+
+    // Consume the next character if it is c
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&predicate) {
+            self.next();
+        }
+    }
+
+    // Column of the next character, counting from 1
+    fn col(&self) -> usize {
+        self.source[self.line_start..self.offset].chars().count() + 1
+    }
 }
 
-fn is_alpha(c: char) -> bool {
-    (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+// Resolve the character(s) following a backslash in a string literal
+fn scan_escape(iter: &mut Cursor) -> Option<char> {
+    Some(match iter.next()? {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        '"' => '"',
+        '\\' => '\\',
+        '$' => '$',
+        'u' => {
+            if iter.next()? != '{' {
+                return None;
+            }
+
+            let start = iter.offset;
+            iter.eat_while(|c| c.is_ascii_hexdigit());
+            let code = &iter.source[start..iter.offset];
+
+            // leave the closing quote alone for malformed escapes like "\u{41"
+            if !iter.eat('}') || code.is_empty() || code.len() > 6 {
+                return None;
+            }
+            char::from_u32(u32::from_str_radix(code, 16).ok()?)?
+        }
+        _ => return None,
+    })
 }
 
-fn get_next_token_type<'a>(
+// Scan a string literal after its opening quote (or after the '}' closing an embedded expression).
+// Stops at the closing quote, yielding a Text, or at the next "${", yielding an Interpolation
+fn scan_string(iter: &mut Cursor, interpolations: &mut Vec<usize>) -> Result<TokenType, usize> {
+    let mut res = String::new();
+    let mut valid = true;
+
+    let token_type = loop {
+        match iter.next() {
+            // closing quote missing
+            None => return Err(1003),
+            Some('"') => break TokenType::Text(res),
+            Some('$') if iter.peek() == Some('{') => {
+                iter.next();
+                interpolations.push(0);
+                break TokenType::Interpolation(res);
+            }
+            Some('\\') => match scan_escape(iter) {
+                Some(c) => res.push(c),
+                None => valid = false,
+            },
+            Some(c) => res.push(c),
+        }
+    };
+
+    if valid {
+        Ok(token_type)
+    } else {
+        Err(1004)
+    }
+}
+
+// The token starting with c, whitespace and comments having been skipped already
+fn get_next_token_type(
     c: char,
-    mut iter: &mut itertools::MultiPeek<Chars<'a>>,
-    mut current: &mut usize,
-    mut line: &mut usize,
+    iter: &mut Cursor,
+    interpolations: &mut Vec<usize>,
 ) -> Result<TokenType, usize> {
     let mut tern = |on: char, then: TokenType, other: TokenType| {
-        *current += 1;
-        if iter.next() == Some(on) {
+        if iter.eat(on) {
             then
         } else {
             other
@@ -38,158 +136,212 @@ fn get_next_token_type<'a>(
     };
 
     Ok(match c {
-        ' ' | '\r' | '\t' => get_next_token_type(
-            iter.next().ok_or::<usize>(5)?,
-            &mut iter,
-            &mut current,
-            &mut line,
-        )?,
-        '\n' => {
-            *line += 1;
-            get_next_token_type(
-                iter.next().ok_or::<usize>(6)?,
-                &mut iter,
-                &mut current,
-                &mut line,
-            )?
-        }
         '(' => TokenType::LeftParen,
         ')' => TokenType::RightParen,
-        '{' => TokenType::LeftBrace,
-        '}' => TokenType::RightBrace,
+        '{' => {
+            if let Some(depth) = interpolations.last_mut() {
+                *depth += 1;
+            }
+            TokenType::LeftBrace
+        }
+        '}' => match interpolations.last_mut() {
+            // closes the embedded expression, the string literal continues after it
+            Some(0) => {
+                interpolations.pop();
+                scan_string(iter, interpolations)?
+            }
+            Some(depth) => {
+                *depth -= 1;
+                TokenType::RightBrace
+            }
+            None => TokenType::RightBrace,
+        },
         ',' => TokenType::Comma,
         '.' => TokenType::Dot,
-        '-' => TokenType::Minus,
-        '+' => TokenType::Plus,
-        ';' => TokenType::Semicolon,
-        '*' => TokenType::Star,
-
-        '!' => tern('=', TokenType::BangEqual, TokenType::Bang),
-        '=' => tern('=', TokenType::EqualEqual, TokenType::Equal),
-        '<' => tern('=', TokenType::LessEqual, TokenType::Less),
-        '>' => tern('=', TokenType::GreaterEqual, TokenType::Greater),
-
-        '/' => {
-            if iter.peek() == Some(&'/') {
+        '-' => match iter.peek() {
+            Some('-') => {
                 iter.next();
-                while let Some(c) = iter.next() {
-                    if c == '\n' {
-                        return get_next_token_type('\n', &mut iter, &mut current, &mut line);
-                    }
-                    *current += 1;
-                }
-                return Err(8);
-            } else {
-                TokenType::Slash
+                TokenType::MinusMinus
             }
-        }
-        '"' => {
-            let mut res = String::new();
-            while let Some(&c) = iter.peek() {
-                if c == '"' {
-                    iter.reset_peek();
-                    break;
-                }
-
-                if c == '\n' {
-                    *line += 1;
-                }
-                res.push(c);
+            Some('=') => {
                 iter.next();
+                TokenType::MinusEqual
             }
-
-            // closing quote missing
-            if iter.peek() == None {
-                panic!();
+            _ => TokenType::Minus,
+        },
+        '+' => match iter.peek() {
+            Some('+') => {
+                iter.next();
+                TokenType::PlusPlus
             }
-
-            // skip closing quote
-            iter.next();
-
-            TokenType::Text(res)
-        }
-        c if is_digit(c) => {
-            let mut res = c.to_string();
-            while let Some(&next_c) = iter.peek() {
-                if !is_digit(next_c) {
-                    iter.reset_peek();
-                    break;
-                }
-
-                res.push(next_c);
+            Some('=') => {
                 iter.next();
+                TokenType::PlusEqual
             }
+            _ => TokenType::Plus,
+        },
+        ';' => TokenType::Semicolon,
+        '?' => TokenType::Question,
+        ':' => TokenType::Colon,
+        '*' => tern('=', TokenType::StarEqual, TokenType::Star),
 
-            if iter.peek() == Some(&'.') && is_digit(*iter.peek().unwrap_or(&'x')) {
+        '!' => tern('=', TokenType::BangEqual, TokenType::Bang),
+        '=' => match iter.peek() {
+            Some('=') => {
                 iter.next();
-                res.push('.');
-
-                while let Some(&next_c) = iter.peek() {
-                    if !is_digit(next_c) {
-                        iter.reset_peek();
-                        break;
-                    }
-
-                    res.push(next_c);
-                    iter.next();
-                }
+                TokenType::EqualEqual
             }
-
-            if let Ok(f) = res.parse::<f64>() {
-                TokenType::Number(res)
-            } else {
-                panic!();
-                // todo return an Error token here
+            Some('>') => {
+                iter.next();
+                TokenType::FatArrow
             }
-        }
-        c if is_alpha(c) => {
-            let mut res = c.to_string();
-            while let Some(&c) = iter.peek() {
-                if !(is_alpha(c) || is_digit(c)) {
-                    iter.reset_peek();
-                    break;
-                }
-
-                res.push(c);
+            _ => TokenType::Equal,
+        },
+        '<' => tern('=', TokenType::LessEqual, TokenType::Less),
+        '>' => tern('=', TokenType::GreaterEqual, TokenType::Greater),
+        '/' => tern('=', TokenType::SlashEqual, TokenType::Slash),
+        '"' => scan_string(iter, interpolations)?,
+        c if is_digit(c) => {
+            let start = iter.offset - 1;
+            iter.eat_while(is_digit);
+            if iter.peek() == Some('.') && iter.peek_second().is_some_and(is_digit) {
                 iter.next();
+                iter.eat_while(is_digit);
             }
+            TokenType::Number(iter.source[start..iter.offset].to_owned())
+        }
+        c if is_alpha(c) => {
+            let start = iter.offset - c.len_utf8();
+            iter.eat_while(|c| is_alpha(c) || is_digit(c));
+            let res = &iter.source[start..iter.offset];
 
-            if let Some(keyword_token) = keyword_to_token_type(&res) {
+            if let Some(keyword_token) = keyword_to_token_type(res) {
                 keyword_token
             } else {
-                TokenType::Identifier(res)
+                TokenType::Identifier(res.to_owned())
             }
         }
-        // todo handle unsupported characters with an error type or something
         _ => return Err(1002),
     })
 }
 
-fn scan_token<'a>(
-    c: char,
-    mut iter: &mut itertools::MultiPeek<Chars<'a>>,
-    mut current: &mut usize,
-    mut line: &mut usize,
-) -> Result<Token, usize> {
-    let token_type =  get_next_token_type(c, &mut iter, &mut current, &mut line)?;
-    Ok(Token {
-        token_type,
-        line: line.clone(),
-        col: if *current == 0 { 0 } else { *current - 1 },
-    })
+pub fn describe(code: usize) -> &'static str {
+    match code {
+        1002 => "Unexpected character.",
+        1003 => "Unterminated string.",
+        1004 => "Invalid escape sequence.",
+        _ => "Invalid token.",
+    }
 }
 
-pub fn scan_tokens<'a>(input_chars: Chars<'a>) -> Vec<Token> {
-    let mut input_mpeek = itertools::multipeek(input_chars);
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexemeKind {
+    // spaces, tabs and line breaks
+    Whitespace,
+    // a `//` comment, without the line break ending it
+    Comment,
+    Token(Token),
+    // text that doesn't scan, with the code of the problem
+    Error(usize),
+}
+
+// A piece of the source, the lexemes of a source together are exactly its text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lexeme {
+    pub kind: LexemeKind,
+    pub text: String,
+    // where the lexeme starts, line and column counting from 1
+    pub offset: usize,
+    pub line: usize,
+    pub col: usize,
+}
 
-    let mut current = 0;
-    let mut line = 1;
+// Every byte of the source, trivia and text in error included
+pub fn scan_lexemes(source: &str) -> Vec<Lexeme> {
+    let mut iter = Cursor {
+        source,
+        offset: 0,
+        line: 1,
+        line_start: 0,
+    };
+    // brace depth of each string interpolation we are currently inside of
+    let mut interpolations = vec![];
 
     let mut out = vec![];
-    while let Some(c) = input_mpeek.next() {
-        if let Ok(t) = scan_token(c, &mut input_mpeek, &mut current, &mut line) {
-            out.push(t)
-        }
+    while let Some(c) = iter.peek() {
+        let (offset, line, col) = (iter.offset, iter.line, iter.col());
+        let kind = if matches!(c, ' ' | '\r' | '\t' | '\n') {
+            iter.eat_while(|c| matches!(c, ' ' | '\r' | '\t' | '\n'));
+            LexemeKind::Whitespace
+        } else if c == '/' && iter.peek_second() == Some('/') {
+            iter.eat_while(|c| c != '\n');
+            LexemeKind::Comment
+        } else {
+            iter.next();
+            match get_next_token_type(c, &mut iter, &mut interpolations) {
+                // tokens carry the line they end on, which differs for multiline strings
+                Ok(token_type) => LexemeKind::Token(Token {
+                    token_type,
+                    line: iter.line,
+                    col,
+                }),
+                Err(e) => LexemeKind::Error(e),
+            }
+        };
+        out.push(Lexeme {
+            kind,
+            text: source[offset..iter.offset].to_owned(),
+            offset,
+            line,
+            col,
+        });
     }
     out
 }
+
+// Tokens of the input, and the problems of the parts that couldn't be scanned
+pub fn scan_tokens(input_chars: Chars<'_>) -> (Vec<Token>, Vec<Diagnostic>) {
+    let (tokens, errors, _) = split_lexemes(&scan_lexemes(input_chars.as_str()));
+    (tokens, errors)
+}
+
+// The tokens among the lexemes, the problems of those in error and the index
+// of the lexeme of every token
+pub fn split_lexemes(lexemes: &[Lexeme]) -> (Vec<Token>, Vec<Diagnostic>, Vec<usize>) {
+    let mut out = vec![];
+    let mut errors = vec![];
+    let mut indices = vec![];
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        match &lexeme.kind {
+            LexemeKind::Token(token) => {
+                out.push(token.clone());
+                indices.push(i);
+            }
+            LexemeKind::Error(e) => {
+                // at the end of the problem, like the line of a token
+                let line = lexeme.line + lexeme.text.matches('\n').count();
+                errors.push(Diagnostic {
+                    line,
+                    message: describe(*e).to_owned(),
+                });
+                // a string with a bad escape is still a string, the parser
+                // shouldn't report the gap it would leave
+                if *e == 1004 {
+                    let token_type = if lexeme.text.ends_with("${") {
+                        TokenType::Interpolation(String::new())
+                    } else {
+                        TokenType::Text(String::new())
+                    };
+                    out.push(Token {
+                        token_type,
+                        line,
+                        col: lexeme.col,
+                    });
+                    indices.push(i);
+                }
+            }
+            LexemeKind::Whitespace | LexemeKind::Comment => {}
+        }
+    }
+    (out, errors, indices)
+}
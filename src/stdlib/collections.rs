@@ -0,0 +1,141 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::to_index;
+use crate::globals::Globals;
+use crate::host::HostObject;
+use crate::limits::Memory;
+use crate::native::NativeFunction;
+use crate::value::Value;
+
+// Bytes charged to the memory budget for each item of a list or map
+pub const ITEM_SIZE: usize = std::mem::size_of::<Value>();
+
+// Growable sequence of values, what Rust Vecs become in Lox
+#[derive(Debug, Default)]
+pub struct List {
+    pub items: RefCell<Vec<Value>>,
+}
+
+impl List {
+    pub fn new(items: Vec<Value>) -> Self {
+        List {
+            items: RefCell::new(items),
+        }
+    }
+
+    fn index(&self, n: f64) -> Result<usize, String> {
+        let index = to_index(n)?;
+        let len = self.items.borrow().len();
+        if index >= len {
+            return Err(format!(
+                "Index {} is out of range for a list of length {}.",
+                index, len
+            ));
+        }
+        Ok(index)
+    }
+}
+
+impl HostObject for List {
+    fn class_name(&self) -> &str {
+        "List"
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        match name {
+            "length" => Some(Value::Number(self.items.borrow().len() as f64)),
+            _ => None,
+        }
+    }
+
+    fn method(self: Rc<Self>, name: &str) -> Option<NativeFunction> {
+        let method = match name {
+            "get" => NativeFunction::new(name, move |index: f64| {
+                let index = self.index(index)?;
+                Ok::<_, String>(self.items.borrow()[index].clone())
+            }),
+            "set" => NativeFunction::new(name, move |index: f64, value: Value| {
+                let index = self.index(index)?;
+                self.items.borrow_mut()[index] = value;
+                Ok::<_, String>(())
+            }),
+            "push" => NativeFunction::new(name, move |memory: Memory, value: Value| {
+                memory.allocate(ITEM_SIZE)?;
+                self.items.borrow_mut().push(value);
+                Ok::<_, String>(())
+            }),
+            // nil for an empty list
+            "pop" => NativeFunction::new(name, move || self.items.borrow_mut().pop()),
+            _ => return None,
+        };
+        Some(method)
+    }
+}
+
+// Values keyed by strings, what Rust HashMaps become in Lox
+#[derive(Debug, Default)]
+pub struct Map {
+    pub entries: RefCell<HashMap<String, Value>>,
+}
+
+impl Map {
+    pub fn new(entries: HashMap<String, Value>) -> Self {
+        Map {
+            entries: RefCell::new(entries),
+        }
+    }
+}
+
+impl HostObject for Map {
+    fn class_name(&self) -> &str {
+        "Map"
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        match name {
+            "length" => Some(Value::Number(self.entries.borrow().len() as f64)),
+            _ => None,
+        }
+    }
+
+    fn method(self: Rc<Self>, name: &str) -> Option<NativeFunction> {
+        let method = match name {
+            // nil for missing keys
+            "get" => NativeFunction::new(name, move |key: String| {
+                self.entries.borrow().get(&key).cloned()
+            }),
+            "set" => NativeFunction::new(name, move |memory: Memory, key: String, value: Value| {
+                if !self.entries.borrow().contains_key(&key) {
+                    memory.allocate(key.len() + ITEM_SIZE)?;
+                }
+                self.entries.borrow_mut().insert(key, value);
+                Ok::<_, String>(())
+            }),
+            "has" => NativeFunction::new(name, move |key: String| {
+                self.entries.borrow().contains_key(&key)
+            }),
+            // the removed value, nil if there was none
+            "remove" => NativeFunction::new(name, move |key: String| {
+                self.entries.borrow_mut().remove(&key)
+            }),
+            // sorted, so iterating over them is deterministic
+            "keys" => NativeFunction::new(name, move |memory: Memory| {
+                let mut keys: Vec<_> = self.entries.borrow().keys().cloned().collect();
+                let bytes: usize = keys.iter().map(|key| key.len() + ITEM_SIZE).sum();
+                memory.allocate(bytes)?;
+                keys.sort();
+                let items = keys.into_iter().map(Value::Text).collect();
+                Ok::<_, String>(Rc::new(List::new(items)))
+            }),
+            _ => return None,
+        };
+        Some(method)
+    }
+}
+
+pub fn register(globals: &mut Globals) {
+    globals.define("List", || Rc::new(List::default()));
+    globals.define("Map", || Rc::new(Map::default()));
+}
@@ -0,0 +1,98 @@
+use std::fs;
+use std::io::{BufRead, Write};
+
+use super::collections::ITEM_SIZE;
+use crate::callable::Arity;
+use crate::globals::Globals;
+use crate::interpreter::Interpreter;
+use crate::limits::Memory;
+use crate::permissions::Permissions;
+use crate::value::{Er, Value};
+
+// Relative paths are resolved against the working directory, not the script
+
+fn os_error(path: &str, error: std::io::Error) -> String {
+    format!("{}: {}", path, error)
+}
+
+fn read_file(permissions: Permissions, path: String) -> Result<String, String> {
+    permissions.check_read(&path)?;
+    fs::read_to_string(&path).map_err(|e| os_error(&path, e))
+}
+
+fn write_file(permissions: Permissions, path: String, text: String) -> Result<(), String> {
+    permissions.check_write(&path)?;
+    fs::write(&path, text).map_err(|e| os_error(&path, e))
+}
+
+fn append_file(permissions: Permissions, path: String, text: String) -> Result<(), String> {
+    permissions.check_write(&path)?;
+    fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| os_error(&path, e))
+}
+
+fn file_exists(permissions: Permissions, path: String) -> Result<bool, String> {
+    permissions.check_read(&path)?;
+    Ok(std::path::Path::new(&path).exists())
+}
+
+// Names of the entries, sorted
+fn list_dir(permissions: Permissions, memory: Memory, path: String) -> Result<Vec<String>, String> {
+    permissions.check_read(&path)?;
+    let mut names = fs::read_dir(&path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| os_error(&path, e))?;
+    memory.allocate(names.iter().map(|name| name.len() + ITEM_SIZE).sum())?;
+    names.sort();
+    Ok(names)
+}
+
+// Next line of input without its line ending, nil at end of input
+fn read_line(interpreter: &mut Interpreter, _: Vec<Value>) -> Result<Value, Er> {
+    let input = &mut interpreter.streams.input;
+    let line = interpreter.sources.line(|| {
+        let mut line = String::new();
+        let read = input
+            .read_line(&mut line)
+            .map_err(|e| os_error("input", e))?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    });
+    match line {
+        Ok(line) => Ok(line.map_or(Value::Nil, Value::Text)),
+        Err(e) => Err(interpreter.error(e)),
+    }
+}
+
+fn eprint(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, Er> {
+    let text = args.first().map(Value::to_string).unwrap_or_default();
+    writeln!(interpreter.streams.error, "{}", text)
+        .map_err(|e| interpreter.error(os_error("error output", e)))?;
+    Ok(Value::Nil)
+}
+
+pub fn register(globals: &mut Globals) {
+    globals.define("readFile", read_file);
+    globals.define("writeFile", write_file);
+    globals.define("appendFile", append_file);
+    globals.define("fileExists", file_exists);
+    globals.define("listDir", list_dir);
+    globals.define_raw("readLine", Arity::exact(0), read_line);
+    globals.define_raw("eprint", Arity::exact(1), eprint);
+}
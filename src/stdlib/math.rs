@@ -0,0 +1,137 @@
+use crate::callable::Arity;
+use crate::globals::Globals;
+use crate::interpreter::Interpreter;
+use crate::native::{Args, Rest};
+use crate::value::{Er, Value};
+
+// SplitMix64, small and good enough for scripts. Lives in the interpreter so
+// all modules draw from the same sequence and seed() affects every one of them
+#[derive(Debug, Default)]
+pub struct Random {
+    // None until seeded, by seed() or from the session's sources on first use
+    state: Option<u64>,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { state: Some(seed) }
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        self.state = Some(seed);
+    }
+
+    pub fn is_seeded(&self) -> bool {
+        self.state.is_some()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let state = self
+            .state
+            .unwrap_or_default()
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        self.state = Some(state);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1), using the top 53 bits so every value is exact
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// Unseeded runs differ between invocations unless replayed, call seed() for
+// reproducible ones
+fn next_random(interpreter: &mut Interpreter) -> Result<f64, String> {
+    if !interpreter.random.is_seeded() {
+        let seed = interpreter.sources.seed()?;
+        interpreter.random.seed(seed);
+    }
+    Ok(interpreter.random.next_f64())
+}
+
+// Integers beyond 2^53 aren't exact as numbers, so bounds are kept within it
+const MAX_INTEGER: f64 = 9007199254740992.0;
+
+fn to_integer(n: f64) -> Result<i64, String> {
+    if n.fract() != 0.0 || !n.is_finite() {
+        Err(format!("Expected an integer but got {}.", n))
+    } else if n.abs() > MAX_INTEGER {
+        Err(format!("{} is out of the range of exact integers.", n))
+    } else {
+        Ok(n as i64)
+    }
+}
+
+fn random_int(interpreter: &mut Interpreter, lo: f64, hi: f64) -> Result<f64, String> {
+    let (lo, hi) = (to_integer(lo)?, to_integer(hi)?);
+    if lo > hi {
+        return Err(format!(
+            "Lower bound {} is greater than upper bound {}.",
+            lo, hi
+        ));
+    }
+    let span = (hi - lo + 1) as f64;
+    Ok(lo as f64 + (next_random(interpreter)? * span).floor())
+}
+
+fn extreme(values: Vec<f64>, pick: fn(f64, f64) -> f64) -> f64 {
+    values.into_iter().reduce(pick).unwrap_or(f64::NAN)
+}
+
+pub fn register(globals: &mut Globals) {
+    globals.constant("PI", Value::Number(std::f64::consts::PI));
+    globals.constant("E", Value::Number(std::f64::consts::E));
+
+    globals.define("sqrt", f64::sqrt);
+    globals.define("pow", f64::powf);
+    globals.define("abs", f64::abs);
+    globals.define("floor", f64::floor);
+    globals.define("ceil", f64::ceil);
+    globals.define("round", f64::round);
+    globals.define("min", |first: f64, Rest(rest): Rest<f64>| {
+        f64::min(first, extreme(rest, f64::min))
+    });
+    globals.define("max", |first: f64, Rest(rest): Rest<f64>| {
+        f64::max(first, extreme(rest, f64::max))
+    });
+
+    globals.define("sin", f64::sin);
+    globals.define("cos", f64::cos);
+    globals.define("tan", f64::tan);
+    globals.define("asin", f64::asin);
+    globals.define("acos", f64::acos);
+    globals.define("atan", f64::atan);
+    globals.define("atan2", f64::atan2);
+
+    globals.define("log", f64::ln);
+    globals.define("log2", f64::log2);
+    globals.define("log10", f64::log10);
+    globals.define("exp", f64::exp);
+
+    globals.define_raw("random", Arity::exact(0), |interpreter, _| {
+        next_random(interpreter)
+            .map(Value::Number)
+            .map_err(|e| interpreter.error(e))
+    });
+    globals.define_raw("randomInt", Arity::exact(2), |interpreter, values| {
+        let mut args = Args::new("randomInt", values);
+        let (lo, hi) = args
+            .next()
+            .and_then(|lo| Ok((lo, args.next()?)))
+            .map_err(|e| interpreter.error(e))?;
+        random_int(interpreter, lo, hi)
+            .map(Value::Number)
+            .map_err(|e| interpreter.error(e))
+    });
+    globals.define_raw("seed", Arity::exact(1), |interpreter, values| {
+        let n: f64 = Args::new("seed", values)
+            .next()
+            .map_err(|e| interpreter.error(e))?;
+        interpreter.random.seed(n.to_bits());
+        Ok::<_, Er>(Value::Nil)
+    });
+}
@@ -0,0 +1,14 @@
+pub mod collections;
+pub mod io;
+pub mod math;
+pub mod string;
+pub mod types;
+
+// Check that a number is a valid index or count, i.e. a non-negative integer
+fn to_index(n: f64) -> Result<usize, String> {
+    if n < 0.0 || n.fract() != 0.0 || !n.is_finite() {
+        Err(format!("Expected a non-negative integer but got {}.", n))
+    } else {
+        Ok(n as usize)
+    }
+}
@@ -0,0 +1,197 @@
+use super::collections::ITEM_SIZE;
+use super::to_index;
+use crate::globals::Globals;
+use crate::limits::Memory;
+use crate::native::{FromLox, Rest};
+use crate::value::Value;
+
+// Indices count characters, not bytes, so "ä" has length 1
+
+fn char_index(text: &str, n: f64) -> Result<usize, String> {
+    let index = to_index(n)?;
+    let len = text.chars().count();
+    if index > len {
+        return Err(format!(
+            "Index {} is out of range for a string of length {}.",
+            index, len
+        ));
+    }
+    Ok(index)
+}
+
+fn len(text: String) -> usize {
+    text.chars().count()
+}
+
+fn substring(text: String, start: f64, end: Option<f64>) -> Result<String, String> {
+    let start = char_index(&text, start)?;
+    let end = match end {
+        Some(end) => char_index(&text, end)?,
+        None => text.chars().count(),
+    };
+    if start > end {
+        return Err(format!("Start {} is after end {}.", start, end));
+    }
+    Ok(text.chars().skip(start).take(end - start).collect())
+}
+
+fn char_at(text: String, index: f64) -> Result<String, String> {
+    let index = to_index(index)?;
+    text.chars().nth(index).map(String::from).ok_or_else(|| {
+        format!(
+            "Index {} is out of range for a string of length {}.",
+            index,
+            text.chars().count()
+        )
+    })
+}
+
+// Character index of the first occurrence at or after from, -1 if there is none
+fn index_of(text: String, needle: String, from: Option<f64>) -> Result<f64, String> {
+    let from = char_index(&text, from.unwrap_or(0.0))?;
+    let offset: usize = text.chars().take(from).map(char::len_utf8).sum();
+    Ok(match text[offset..].find(&needle) {
+        Some(byte) => (from + text[offset..offset + byte].chars().count()) as f64,
+        None => -1.0,
+    })
+}
+
+fn contains(text: String, needle: String) -> bool {
+    text.contains(&needle)
+}
+
+fn starts_with(text: String, prefix: String) -> bool {
+    text.starts_with(&prefix)
+}
+
+fn ends_with(text: String, suffix: String) -> bool {
+    text.ends_with(&suffix)
+}
+
+fn count(text: String, needle: String) -> Result<usize, String> {
+    if needle.is_empty() {
+        return Err("Cannot count empty strings.".to_owned());
+    }
+    Ok(text.matches(&needle).count())
+}
+
+fn upper(text: String) -> String {
+    text.to_uppercase()
+}
+
+fn lower(text: String) -> String {
+    text.to_lowercase()
+}
+
+fn trim(text: String) -> String {
+    text.trim().to_owned()
+}
+
+fn trim_start(text: String) -> String {
+    text.trim_start().to_owned()
+}
+
+fn trim_end(text: String) -> String {
+    text.trim_end().to_owned()
+}
+
+fn replace(memory: Memory, text: String, from: String, to: String) -> Result<String, String> {
+    if from.is_empty() {
+        return Err("Cannot replace empty strings.".to_owned());
+    }
+    // each replacement may grow the text by the length of to
+    let grown = text.matches(&from).count().saturating_mul(to.len());
+    memory.reserve(text.len().saturating_add(grown))?;
+    Ok(text.replace(&from, &to))
+}
+
+// The parts between the separators, count(text, separator) + 1 of them
+fn split(memory: Memory, text: String, separator: String) -> Result<Vec<String>, String> {
+    if separator.is_empty() {
+        return Err("Cannot split on an empty separator.".to_owned());
+    }
+    let parts = text.matches(&separator).count() + 1;
+    memory.allocate(text.len() + parts * ITEM_SIZE)?;
+    Ok(text.split(&separator).map(String::from).collect())
+}
+
+// A string to join, or a list of them such as split returns
+enum Part {
+    Text(String),
+    List(Vec<String>),
+}
+
+impl FromLox for Part {
+    fn expected() -> String {
+        "string or list of strings".to_owned()
+    }
+    fn from_lox(value: Value) -> Option<Self> {
+        match value {
+            Value::Text(s) => Some(Part::Text(s)),
+            value => Vec::from_lox(value).map(Part::List),
+        }
+    }
+}
+
+fn join(separator: String, parts: Rest<Part>) -> String {
+    let mut texts = vec![];
+    for part in parts.0 {
+        match part {
+            Part::Text(text) => texts.push(text),
+            Part::List(list) => texts.extend(list),
+        }
+    }
+    texts.join(&separator)
+}
+
+fn repeat(memory: Memory, text: String, times: f64) -> Result<String, String> {
+    let times = to_index(times)?;
+    let too_long = || format!("Repeating a string {} times is too long.", times);
+    let len = text.len().checked_mul(times).ok_or_else(too_long)?;
+    memory.reserve(len)?;
+    // fail like Lox rather than abort when there is no memory for it
+    let mut res = String::new();
+    res.try_reserve_exact(len).map_err(|_| too_long())?;
+    if !text.is_empty() {
+        for _ in 0..times {
+            res.push_str(&text);
+        }
+    }
+    Ok(res)
+}
+
+fn char_code(text: String, index: Option<f64>) -> Result<f64, String> {
+    let c = char_at(text, index.unwrap_or(0.0))?;
+    Ok(c.chars().next().map_or(0, u32::from) as f64)
+}
+
+fn from_char_code(code: f64) -> Result<String, String> {
+    let c = to_index(code)
+        .ok()
+        .filter(|&code| code <= char::MAX as usize)
+        .and_then(|code| char::from_u32(code as u32))
+        .ok_or_else(|| format!("{} is not a valid character code.", code))?;
+    Ok(c.to_string())
+}
+
+pub fn register(globals: &mut Globals) {
+    globals.define("len", len);
+    globals.define("substring", substring);
+    globals.define("charAt", char_at);
+    globals.define("indexOf", index_of);
+    globals.define("contains", contains);
+    globals.define("startsWith", starts_with);
+    globals.define("endsWith", ends_with);
+    globals.define("count", count);
+    globals.define("upper", upper);
+    globals.define("lower", lower);
+    globals.define("trim", trim);
+    globals.define("trimStart", trim_start);
+    globals.define("trimEnd", trim_end);
+    globals.define("replace", replace);
+    globals.define("split", split);
+    globals.define("join", join);
+    globals.define("repeat", repeat);
+    globals.define("charCode", char_code);
+    globals.define("fromCharCode", from_char_code);
+}
@@ -0,0 +1,58 @@
+use std::rc::Rc;
+
+use crate::callable::Callable;
+use crate::globals::Globals;
+use crate::interpreter::is_truthy;
+use crate::value::Value;
+
+fn type_of(value: Value) -> &'static str {
+    value.type_name()
+}
+
+fn str(value: Value) -> String {
+    value.to_string()
+}
+
+// Numbers pass through, strings that don't hold a number give nil
+fn num(value: Value) -> Result<Option<f64>, String> {
+    match value {
+        Value::Number(n) => Ok(Some(n)),
+        Value::Text(text) => Ok(parse_number(text.trim())),
+        value => Err(format!("Can't convert {} to a number.", value.type_name())),
+    }
+}
+
+// Same syntax as number literals plus a sign, so "inf", "1e3" or "" aren't numbers
+fn parse_number(text: &str) -> Option<f64> {
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (int, fract) = digits.split_once('.').unwrap_or((digits, "0"));
+    let is_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    if is_digits(int) && is_digits(fract) {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn bool(value: Value) -> bool {
+    is_truthy(&value)
+}
+
+// nil for natives taking optional or variadic arguments
+fn arity(function: Rc<dyn Callable>) -> Option<usize> {
+    let arity = function.arity();
+    arity.max.filter(|&max| max == arity.min)
+}
+
+fn name(function: Rc<dyn Callable>) -> String {
+    function.name().to_owned()
+}
+
+pub fn register(globals: &mut Globals) {
+    globals.define("type", type_of);
+    globals.define("str", str);
+    globals.define("num", num);
+    globals.define("bool", bool);
+    globals.define("arity", arity);
+    globals.define("name", name);
+}
@@ -0,0 +1,59 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::rc::Rc;
+
+// Where `print` and `eprint` write to and `readLine` reads from
+pub struct Streams {
+    pub output: Box<dyn Write>,
+    pub error: Box<dyn Write>,
+    pub input: Box<dyn BufRead>,
+}
+
+impl Default for Streams {
+    // The ones of the process
+    fn default() -> Self {
+        Streams {
+            output: Box::new(io::stdout()),
+            error: Box::new(io::stderr()),
+            input: Box::new(BufReader::new(io::stdin())),
+        }
+    }
+}
+
+impl fmt::Debug for Streams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Streams")
+    }
+}
+
+// In-memory output whose clones share the contents, so output handed to a
+// session can still be read afterwards
+#[derive(Debug, Clone, Default)]
+pub struct OutputBuffer {
+    bytes: Rc<RefCell<Vec<u8>>>,
+}
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.bytes.borrow()).into_owned()
+    }
+
+    pub fn clear(&self) {
+        self.bytes.borrow_mut().clear()
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
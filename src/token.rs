@@ -1,5 +1,5 @@
-pub fn keyword_to_token_type(s: &String) -> Option<TokenType> {
-    match s.as_str() {
+pub fn keyword_to_token_type(s: &str) -> Option<TokenType> {
+    match s {
         "and" => Some(TokenType::And),
         "class" => Some(TokenType::Class),
         "else" => Some(TokenType::Else),
@@ -16,6 +16,14 @@ pub fn keyword_to_token_type(s: &String) -> Option<TokenType> {
         "true" => Some(TokenType::True),
         "var" => Some(TokenType::Var),
         "while" => Some(TokenType::While),
+        "throw" => Some(TokenType::Throw),
+        "try" => Some(TokenType::Try),
+        "catch" => Some(TokenType::Catch),
+        "finally" => Some(TokenType::Finally),
+        "import" => Some(TokenType::Import),
+        "match" => Some(TokenType::Match),
+        "case" => Some(TokenType::Case),
+        "default" => Some(TokenType::Default),
         _ => None,
     }
 }
@@ -55,6 +63,8 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Question,
+    Colon,
 
     // One or two character tokens.
     Bang,
@@ -65,10 +75,19 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    PlusPlus,
+    MinusMinus,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    FatArrow,
 
     // Literals.
     Identifier(String),
     Text(String),
+    // String segment that is followed by an embedded expression, as in "segment ${expr}"
+    Interpolation(String),
     Number(String),
 
     // Keywords.
@@ -88,8 +107,79 @@ pub enum TokenType {
     True,
     Var,
     While,
+    Throw,
+    Try,
+    Catch,
+    Finally,
+    Import,
+    Match,
+    Case,
+    Default,
 }
 
+impl TokenType {
+    // Name of the kind of token, the same for all identifiers, strings and numbers
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenType::LeftParen => "left_paren",
+            TokenType::RightParen => "right_paren",
+            TokenType::LeftBrace => "left_brace",
+            TokenType::RightBrace => "right_brace",
+            TokenType::Comma => "comma",
+            TokenType::Dot => "dot",
+            TokenType::Minus => "minus",
+            TokenType::Plus => "plus",
+            TokenType::Semicolon => "semicolon",
+            TokenType::Slash => "slash",
+            TokenType::Star => "star",
+            TokenType::Question => "question",
+            TokenType::Colon => "colon",
+            TokenType::Bang => "bang",
+            TokenType::BangEqual => "bang_equal",
+            TokenType::Equal => "equal",
+            TokenType::EqualEqual => "equal_equal",
+            TokenType::Greater => "greater",
+            TokenType::GreaterEqual => "greater_equal",
+            TokenType::Less => "less",
+            TokenType::LessEqual => "less_equal",
+            TokenType::PlusPlus => "plus_plus",
+            TokenType::MinusMinus => "minus_minus",
+            TokenType::PlusEqual => "plus_equal",
+            TokenType::MinusEqual => "minus_equal",
+            TokenType::StarEqual => "star_equal",
+            TokenType::SlashEqual => "slash_equal",
+            TokenType::FatArrow => "fat_arrow",
+            TokenType::Identifier(_) => "identifier",
+            TokenType::Text(_) => "string",
+            TokenType::Interpolation(_) => "interpolation",
+            TokenType::Number(_) => "number",
+            TokenType::And => "and",
+            TokenType::Class => "class",
+            TokenType::Else => "else",
+            TokenType::False => "false",
+            TokenType::Fun => "fun",
+            TokenType::For => "for",
+            TokenType::If => "if",
+            TokenType::Nil => "nil",
+            TokenType::Or => "or",
+            TokenType::Print => "print",
+            TokenType::Return => "return",
+            TokenType::Super => "super",
+            TokenType::This => "this",
+            TokenType::True => "true",
+            TokenType::Var => "var",
+            TokenType::While => "while",
+            TokenType::Throw => "throw",
+            TokenType::Try => "try",
+            TokenType::Catch => "catch",
+            TokenType::Finally => "finally",
+            TokenType::Import => "import",
+            TokenType::Match => "match",
+            TokenType::Case => "case",
+            TokenType::Default => "default",
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Token {
@@ -97,3 +187,13 @@ pub struct Token {
     pub line: usize,
     pub col: usize, // note that this won't be the exact col, just different for meaningful nodes (e.g. two variables called 'a' will have different col if in different locations) ((Todo double check that is true for all cases))
 }
+
+impl Token {
+    // The name of an identifier, empty for the other tokens
+    pub fn name(&self) -> &str {
+        match &self.token_type {
+            TokenType::Identifier(name) => name,
+            _ => "",
+        }
+    }
+}
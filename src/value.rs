@@ -1,42 +1,115 @@
+use std::fmt;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use crate::callable::Callable;
+use crate::host::HostObject;
+use crate::limits::Limit;
 
 #[derive(Debug, Clone)]
 pub enum Er {
     Code(usize),
     Return(Value),
+    // An exception, either thrown by Lox code or raised by a failing operation
+    Throw(Value),
+    // The run used up a resource, this can't be caught by Lox code
+    Limit(Limit),
+    // The debugger ended the run, this can't be caught either
+    Stopped,
+    // A replayed run read something else than was recorded, nor can this
+    Diverged(String),
 }
 
-#[derive(Debug, Clone)]
+// Message of the runtime error codes, used when they get caught by Lox code
+pub fn describe(code: usize) -> &'static str {
+    match code {
+        11 => "Operands must be numbers.",
+        12 => "Operand must be a number.",
+        21 => "Operands must be two numbers or two strings.",
+        14 | 55 => "Unsupported operator.",
+        42 => "Invalid assignment target.",
+        46 | 47 => "Undefined variable.",
+        1060 => "Can only call functions and classes.",
+        1061 => "Wrong number of arguments.",
+        _ => "Internal interpreter error.",
+    }
+}
+
+// Runtime error as seen from Lox, exposing `message` and `line` properties
+#[derive(Debug)]
+pub struct LoxError {
+    pub message: String,
+    pub line: usize,
+}
+
+// Namespace of an imported file, its top-level names are properties of the namespace
+#[derive(Debug)]
+pub struct Module {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Default)]
 pub enum Value {
     Text(String),
     Number(f64),
     Boolean(bool),
+    #[default]
     Nil,
     Callable(Rc<dyn Callable>),
-}
-
-impl Default for Value {
-    fn default() -> Self {
-        Value::Nil
-    }
+    Error(Rc<LoxError>),
+    Module(Rc<Module>),
+    Host(Rc<dyn HostObject>),
 }
 
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
-        self == other
+        match (self, other) {
+            (Self::Text(lhs), Self::Text(rhs)) => lhs == rhs,
+            (Self::Number(lhs), Self::Number(rhs)) => lhs == rhs,
+            (Self::Boolean(lhs), Self::Boolean(rhs)) => lhs == rhs,
+            (Self::Nil, Self::Nil) => true,
+            // functions, errors and instances are only equal to themselves
+            (Self::Callable(lhs), Self::Callable(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Error(lhs), Self::Error(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Host(lhs), Self::Host(rhs)) => Rc::ptr_eq(lhs, rhs),
+            // a file imported twice is the same module
+            (Self::Module(lhs), Self::Module(rhs)) => lhs.path == rhs.path,
+            _ => false,
+        }
     }
 }
 
 impl Value {
-    pub fn to_string(&self) -> String {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Text(_) => "string",
+            Self::Number(_) => "number",
+            Self::Boolean(_) => "bool",
+            Self::Nil => "nil",
+            Self::Callable(_) => "function",
+            Self::Error(_) => "error",
+            Self::Module(_) => "module",
+            Self::Host(_) => "instance",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Text(s) => s.clone(),
-            Self::Number(f) => f.to_string(),
-            Self::Boolean(b) => b.to_string(),
-            Self::Nil => "null".to_string(),
-            Self::Callable(_) => "Callable".to_owned(),
+            Self::Text(s) => write!(f, "{}", s),
+            Self::Number(n) if n.is_infinite() => {
+                write!(f, "{}Infinity", if *n < 0.0 { "-" } else { "" })
+            }
+            // integral numbers print without a decimal point
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Boolean(b) => write!(f, "{}", b),
+            Self::Nil => write!(f, "nil"),
+            Self::Callable(c) => write!(f, "{}", c),
+            Self::Error(e) => write!(f, "{}", e.message),
+            Self::Module(m) => write!(f, "<module {}>", m.name),
+            Self::Host(h) => write!(f, "{} instance", h.class_name()),
         }
     }
 }
@@ -0,0 +1,19 @@
+// Framing of the messages of the language server and the debug adapter
+
+pub fn frame(body: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+// The bodies of the messages a server wrote
+pub fn bodies(output: &str) -> Vec<String> {
+    let mut output = output;
+    let mut bodies = vec![];
+    while let Some(rest) = output.strip_prefix("Content-Length: ") {
+        let (length, rest) = rest.split_once("\r\n\r\n").unwrap();
+        let length: usize = length.parse().unwrap();
+        bodies.push(rest[..length].to_owned());
+        output = &rest[length..];
+    }
+    assert_eq!(output, "");
+    bodies
+}
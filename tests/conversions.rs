@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use lorx::{Diagnostic, Error, FromLox, IntoLox, Lox, Value};
+
+fn session(source: &str) -> Lox {
+    let mut lox = Lox::new();
+    lox.run_source(source).unwrap();
+    lox
+}
+
+#[test]
+fn primitives_round_trip() {
+    let mut lox = session("fun id(x) { return x; }");
+    assert_eq!(lox.call::<f64, _>("id", (2.5,)).unwrap(), 2.5);
+    assert_eq!(lox.call::<i64, _>("id", (-3i64,)).unwrap(), -3);
+    assert!(lox.call::<bool, _>("id", (true,)).unwrap());
+    assert_eq!(
+        lox.call::<String, _>("id", ("text",)).unwrap(),
+        "text".to_owned()
+    );
+    assert_eq!(lox.call::<(), _>("id", ((),)).unwrap(), ());
+}
+
+#[test]
+fn integers_must_be_whole_numbers_in_range() {
+    assert_eq!(i64::from_lox(Value::Number(4.0)), Some(4));
+    assert_eq!(i64::from_lox(Value::Number(4.5)), None);
+    assert_eq!(u32::from_lox(Value::Number(-1.0)), None);
+    assert_eq!(u32::from_lox(Value::Number(4294967295.0)), Some(u32::MAX));
+    assert_eq!(u32::from_lox(Value::Number(4294967296.0)), None);
+    assert_eq!(
+        i64::from_lox(Value::Number(-(2f64.powi(63)))),
+        Some(i64::MIN)
+    );
+    assert_eq!(i64::from_lox(Value::Number(2f64.powi(63))), None);
+    assert_eq!(u64::from_lox(Value::Number(2f64.powi(64))), None);
+    assert_eq!(usize::from_lox(Value::Number(2f64.powi(64))), None);
+    assert_eq!(usize::from_lox(Value::Text("4".to_owned())), None);
+}
+
+#[test]
+fn options_are_nil_or_the_value() {
+    let mut lox = session("fun id(x) { return x; }");
+    assert_eq!(
+        lox.call::<Option<f64>, _>("id", (None::<f64>,)).unwrap(),
+        None
+    );
+    assert_eq!(
+        lox.call::<Option<f64>, _>("id", (Some(1.0),)).unwrap(),
+        Some(1.0)
+    );
+    assert_eq!(None::<f64>.into_lox(), Value::Nil);
+}
+
+#[test]
+fn vecs_become_lists() {
+    let mut lox = session("fun sum(xs) { var s = 0; for (var i = 0; i < xs.length; i++) s = s + xs.get(i); return s; }");
+    assert_eq!(
+        lox.call::<f64, _>("sum", (vec![1.0, 2.0, 3.0],)).unwrap(),
+        6.0
+    );
+
+    let mut lox = session("fun pair(a, b) { var l = List(); l.push(a); l.push(b); return l; }");
+    let pair: Vec<String> = lox.call("pair", ("a", "b")).unwrap();
+    assert_eq!(pair, vec!["a".to_owned(), "b".to_owned()]);
+}
+
+#[test]
+fn hash_maps_become_maps() {
+    let mut config = HashMap::new();
+    config.insert("port".to_owned(), 8080.0);
+    let mut lox = Lox::new();
+    lox.set_global("config", config);
+    assert_eq!(
+        lox.eval_expression("config.get(\"port\")").unwrap(),
+        Value::Number(8080.0)
+    );
+
+    lox.run_source(
+        "fun build() { var m = Map(); m.set(\"a\", true); m.set(\"b\", false); return m; }",
+    )
+    .unwrap();
+    let map: HashMap<String, bool> = lox.call("build", ()).unwrap();
+    assert_eq!(map.len(), 2);
+    assert!(map["a"]);
+    assert!(!map["b"]);
+}
+
+#[test]
+fn lists_returned_by_natives_convert() {
+    let mut lox = session("fun parts(s) { return split(s, \",\"); }");
+    let parts: Vec<String> = lox.call("parts", ("a,b",)).unwrap();
+    assert_eq!(parts, vec!["a".to_owned(), "b".to_owned()]);
+}
+
+#[test]
+fn mismatched_results_are_errors() {
+    let mut lox = session("fun mixed() { var l = List(); l.push(1); l.push(\"two\"); return l; }");
+    assert_eq!(
+        lox.call::<Vec<f64>, _>("mixed", ()).unwrap_err(),
+        Error::Runtime(Diagnostic {
+            line: 0,
+            message: "Expected list of number as result of 'mixed' but got instance.".to_owned()
+        })
+    );
+    assert_eq!(
+        lox.call::<Option<String>, _>("mixed", ()).unwrap_err(),
+        Error::Runtime(Diagnostic {
+            line: 0,
+            message: "Expected string or nil as result of 'mixed' but got instance.".to_owned()
+        })
+    );
+}
+
+#[test]
+fn functions_pass_back_into_lox() {
+    let mut lox = session("fun twice(f, x) { return f(f(x)); }");
+    lox.run_source("fun inc(n) { return n + 1; }").unwrap();
+    let inc = lox.get_global("inc").unwrap();
+    assert_eq!(lox.call::<f64, _>("twice", (inc, 1.0)).unwrap(), 3.0);
+}
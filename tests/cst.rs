@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+
+use lorx::SyntaxTree;
+
+// The .lox files below dir
+fn programs(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            programs(&path, files);
+        } else if path.extension() == Some("lox".as_ref()) {
+            files.push(path);
+        }
+    }
+}
+
+fn text(tree: &SyntaxTree) -> String {
+    tree.root
+        .lexemes()
+        .map(|lexeme| lexeme.text.as_str())
+        .collect()
+}
+
+#[test]
+fn trees_keep_every_byte() {
+    let mut files = vec![];
+    programs(
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/programs")),
+        &mut files,
+    );
+    assert!(files.len() > 100);
+    for file in files {
+        let source = std::fs::read_to_string(&file).unwrap();
+        assert_eq!(
+            text(&SyntaxTree::parse(&source)),
+            source,
+            "{}",
+            file.display()
+        );
+    }
+}
+
+#[test]
+fn trees_keep_text_in_error() {
+    let source = "var a = @;\n// comment\nprint \"bad \\q ${1 +}\";\nfun (\n\"open";
+    let tree = SyntaxTree::parse(source);
+    assert!(!tree.errors.is_empty());
+    assert_eq!(text(&tree), source);
+}
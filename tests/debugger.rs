@@ -0,0 +1,110 @@
+use std::io::Cursor;
+use std::path::PathBuf;
+
+mod common;
+
+use common::{bodies, frame};
+use lorx::{serve_dap, Console, Debugger, Error, Lox, OutputBuffer};
+
+const PROGRAM: &str = "fun add(a, b) {
+  var sum = a + b;
+  return sum;
+}
+print add(1, 2);
+print \"done\";
+";
+
+// The program in a file of its own, the debuggers show its lines
+fn program(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, PROGRAM).unwrap();
+    path.canonicalize().unwrap()
+}
+
+#[test]
+fn console_runs_a_command_script() {
+    let path = program("lorx_console_test.lox");
+    let commands = "break 3\ncontinue\nlocals\nprint sum * 10\nbacktrace\nnext\nquit\n";
+    let console_output = OutputBuffer::new();
+    let console = Console::new(Cursor::new(commands), console_output.clone());
+    let output = OutputBuffer::new();
+    let mut lox = Lox::with_path(&path);
+    lox.set_output(output.clone());
+    lox.set_debugger(Debugger::new(console, vec![], true));
+
+    let result = lox.run_source(PROGRAM);
+    assert_eq!(result, Err(Error::Stopped { line: 6 }));
+    assert_eq!(output.contents(), "3\n");
+    let path = path.display();
+    assert_eq!(
+        console_output.contents(),
+        format!(
+            "Paused at {path}:1 (entry).
+>    1 | fun add(a, b) {{
+(lorx) Breakpoint at {path}:3.
+(lorx) Paused at {path}:3 (breakpoint).
+>    3 |   return sum;
+(lorx)   sum = 3
+  a = 1
+  b = 2
+(lorx) 30
+(lorx)   #0 add at {path}:3
+  #1 <script> at {path}:5
+(lorx) Paused at {path}:6 (step).
+>    6 | print \"done\";
+(lorx) ",
+            path = path
+        )
+    );
+}
+
+#[test]
+fn dap_stops_at_a_breakpoint() {
+    let path = program("lorx_dap_test.lox");
+    let path = path.display();
+    let requests = [
+        r#"{"seq":1,"type":"request","command":"initialize","arguments":{"adapterID":"lorx"}}"#.to_owned(),
+        format!(
+            r#"{{"seq":2,"type":"request","command":"launch","arguments":{{"program":"{}"}}}}"#,
+            path
+        ),
+        format!(
+            r#"{{"seq":3,"type":"request","command":"setBreakpoints","arguments":{{"source":{{"path":"{}"}},"breakpoints":[{{"line":3}}]}}}}"#,
+            path
+        ),
+        r#"{"seq":4,"type":"request","command":"configurationDone"}"#.to_owned(),
+        r#"{"seq":5,"type":"request","command":"stackTrace","arguments":{"threadId":1}}"#.to_owned(),
+        r#"{"seq":6,"type":"request","command":"variables","arguments":{"variablesReference":1}}"#.to_owned(),
+        r#"{"seq":7,"type":"request","command":"evaluate","arguments":{"expression":"sum * 10","frameId":0}}"#.to_owned(),
+        r#"{"seq":8,"type":"request","command":"continue","arguments":{"threadId":1}}"#.to_owned(),
+        r#"{"seq":9,"type":"request","command":"disconnect"}"#.to_owned(),
+    ];
+    let input: String = requests.iter().map(|request| frame(request)).collect();
+    let output = OutputBuffer::new();
+    serve_dap(Cursor::new(input), output.clone()).unwrap();
+
+    let source = format!(r#"{{"name":"lorx_dap_test.lox","path":"{}"}}"#, path);
+    assert_eq!(
+        bodies(&output.contents()),
+        vec![
+            r#"{"seq":1,"type":"response","request_seq":1,"command":"initialize","success":true,"body":{"supportsConfigurationDoneRequest":true,"supportsConditionalBreakpoints":true,"supportsEvaluateForHovers":true,"supportsTerminateRequest":true}}"#.to_owned(),
+            r#"{"seq":2,"type":"event","event":"initialized"}"#.to_owned(),
+            r#"{"seq":3,"type":"response","request_seq":2,"command":"launch","success":true}"#.to_owned(),
+            r#"{"seq":4,"type":"response","request_seq":3,"command":"setBreakpoints","success":true,"body":{"breakpoints":[{"verified":true,"line":3}]}}"#.to_owned(),
+            r#"{"seq":5,"type":"response","request_seq":4,"command":"configurationDone","success":true}"#.to_owned(),
+            r#"{"seq":6,"type":"event","event":"stopped","body":{"reason":"breakpoint","threadId":1,"allThreadsStopped":true}}"#.to_owned(),
+            format!(
+                r#"{{"seq":7,"type":"response","request_seq":5,"command":"stackTrace","success":true,"body":{{"totalFrames":2,"stackFrames":[{{"id":0,"name":"add","source":{},"line":3,"column":1}},{{"id":1,"name":"<script>","source":{},"line":5,"column":1}}]}}}}"#,
+                source, source
+            ),
+            r#"{"seq":8,"type":"response","request_seq":6,"command":"variables","success":true,"body":{"variables":[{"name":"sum","value":"3","type":"number","variablesReference":0},{"name":"a","value":"1","type":"number","variablesReference":0},{"name":"b","value":"2","type":"number","variablesReference":0}]}}"#.to_owned(),
+            r#"{"seq":9,"type":"response","request_seq":7,"command":"evaluate","success":true,"body":{"result":"30","type":"number","variablesReference":0}}"#.to_owned(),
+            r#"{"seq":10,"type":"response","request_seq":8,"command":"continue","success":true,"body":{"allThreadsContinued":true}}"#.to_owned(),
+            r#"{"seq":11,"type":"event","event":"output","body":{"category":"stdout","output":"3\n"}}"#.to_owned(),
+            r#"{"seq":12,"type":"event","event":"output","body":{"category":"stdout","output":"done\n"}}"#.to_owned(),
+            r#"{"seq":13,"type":"event","event":"exited","body":{"exitCode":0}}"#.to_owned(),
+            r#"{"seq":14,"type":"event","event":"terminated"}"#.to_owned(),
+            r#"{"seq":15,"type":"response","request_seq":9,"command":"disconnect","success":true}"#.to_owned(),
+        ]
+    );
+}
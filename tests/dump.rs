@@ -0,0 +1,49 @@
+use lorx::{ast_json, ast_sexp, tokens_json, tokens_text, SYNTAX_VERSION};
+
+// The dumps of version 1 of the syntax, tools rely on them staying the same.
+// Changing them means raising SYNTAX_VERSION and regenerating the files with
+// `lorx tokens` and `lorx ast`
+const SYNTAX: &str = include_str!("golden/syntax.lox");
+const ERRORS: &str = include_str!("golden/errors.lox");
+
+#[test]
+fn version_is_pinned() {
+    assert_eq!(SYNTAX_VERSION, 1);
+}
+
+#[test]
+fn tokens_as_text() {
+    assert_eq!(tokens_text(SYNTAX), include_str!("golden/syntax.tokens"));
+    assert_eq!(tokens_text(ERRORS), include_str!("golden/errors.tokens"));
+}
+
+#[test]
+fn tokens_as_json() {
+    assert_eq!(
+        tokens_json(SYNTAX),
+        include_str!("golden/syntax.tokens.json").trim_end()
+    );
+    assert_eq!(
+        tokens_json(ERRORS),
+        include_str!("golden/errors.tokens.json").trim_end()
+    );
+}
+
+#[test]
+fn ast_as_s_expressions() {
+    assert_eq!(ast_sexp(SYNTAX).unwrap(), include_str!("golden/syntax.ast"));
+}
+
+#[test]
+fn ast_as_json() {
+    assert_eq!(
+        ast_json(SYNTAX).unwrap(),
+        include_str!("golden/syntax.ast.json").trim_end()
+    );
+}
+
+#[test]
+fn ast_of_syntax_errors() {
+    assert!(ast_sexp(ERRORS).is_err());
+    assert!(ast_json(ERRORS).is_err());
+}
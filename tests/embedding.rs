@@ -0,0 +1,227 @@
+use std::io::BufWriter;
+
+use lorx::{Diagnostic, Error, Lox, OutputBuffer, Permissions, Value};
+
+fn diagnostic(line: usize, message: &str) -> Diagnostic {
+    Diagnostic {
+        line,
+        message: message.to_owned(),
+    }
+}
+
+#[test]
+fn run_source_keeps_globals_between_runs() {
+    let mut lox = Lox::new();
+    lox.run_source("var a = 1;").unwrap();
+    lox.run_source("var b = a + 1;").unwrap();
+    assert_eq!(lox.get_global("b"), Some(Value::Number(2.0)));
+}
+
+#[test]
+fn run_source_reports_syntax_errors_without_running() {
+    let mut lox = Lox::new();
+    let error = lox.run_source("var a = 1;\nprint ;").unwrap_err();
+    assert_eq!(
+        error,
+        Error::Syntax(vec![diagnostic(2, "Expect expression.")])
+    );
+    assert_eq!(lox.get_global("a"), None);
+}
+
+#[test]
+fn run_source_reports_resolve_errors() {
+    let mut lox = Lox::new();
+    let error = lox.run_source("{\n  var a = a;\n}").unwrap_err();
+    assert_eq!(
+        error,
+        Error::Syntax(vec![diagnostic(
+            2,
+            "Can't read local variable in its own initializer."
+        )])
+    );
+}
+
+#[test]
+fn run_source_stops_at_runtime_errors() {
+    let mut lox = Lox::new();
+    let error = lox
+        .run_source("var a = 1;\nvar b = -\"x\";\nvar c = 3;")
+        .unwrap_err();
+    assert_eq!(
+        error,
+        Error::Runtime(diagnostic(2, "Operand must be a number."))
+    );
+    assert_eq!(lox.get_global("a"), Some(Value::Number(1.0)));
+    assert_eq!(lox.get_global("c"), None);
+}
+
+#[test]
+fn eval_expression_sees_globals() {
+    let mut lox = Lox::new();
+    lox.run_source("var name = \"lox\";").unwrap();
+    assert_eq!(
+        lox.eval_expression("\"hello \" + name").unwrap(),
+        Value::Text("hello lox".to_owned())
+    );
+    assert_eq!(
+        lox.eval_expression("len(name) * 2").unwrap(),
+        Value::Number(6.0)
+    );
+}
+
+#[test]
+fn eval_expression_rejects_statements() {
+    let mut lox = Lox::new();
+    assert!(matches!(
+        lox.eval_expression("var a = 1;"),
+        Err(Error::Syntax(_))
+    ));
+    assert!(matches!(lox.eval_expression("1 +"), Err(Error::Syntax(_))));
+}
+
+#[test]
+fn set_global_is_visible_to_scripts() {
+    let mut lox = Lox::new();
+    lox.set_global("limit", 3.0);
+    lox.set_global("label", "items");
+    lox.run_source("var summary = str(limit) + \" \" + label;")
+        .unwrap();
+    assert_eq!(
+        lox.get_global("summary"),
+        Some(Value::Text("3 items".to_owned()))
+    );
+}
+
+#[test]
+fn get_global_of_undefined_name() {
+    let lox = Lox::new();
+    assert_eq!(lox.get_global("missing"), None);
+}
+
+#[test]
+fn call_converts_arguments_and_result() {
+    let mut lox = Lox::new();
+    lox.run_source("fun add(a, b) { return a + b; }").unwrap();
+    let sum: f64 = lox.call("add", (1.5, 2.0)).unwrap();
+    assert_eq!(sum, 3.5);
+    let text: String = lox.call("add", ("a", "b")).unwrap();
+    assert_eq!(text, "ab");
+}
+
+#[test]
+fn call_reports_conversion_failures() {
+    let mut lox = Lox::new();
+    lox.run_source("fun greet(name) { return \"hi \" + name; }")
+        .unwrap();
+    let error = lox.call::<f64, _>("greet", ("Ada",)).unwrap_err();
+    assert_eq!(
+        error,
+        Error::Runtime(diagnostic(
+            0,
+            "Expected number as result of 'greet' but got string."
+        ))
+    );
+}
+
+#[test]
+fn call_reports_bad_callees_and_arity() {
+    let mut lox = Lox::new();
+    lox.run_source("var x = 1; fun one(a) { return a; }")
+        .unwrap();
+    assert_eq!(
+        lox.call::<Value, _>("missing", ()).unwrap_err(),
+        Error::Runtime(diagnostic(0, "Undefined variable 'missing'."))
+    );
+    assert_eq!(
+        lox.call::<Value, _>("x", ()).unwrap_err(),
+        Error::Runtime(diagnostic(0, "Can't call 'x', it is a number."))
+    );
+    assert_eq!(
+        lox.call::<Value, _>("one", (1.0, 2.0)).unwrap_err(),
+        Error::Runtime(diagnostic(0, "Expected 1 arguments but got 2."))
+    );
+}
+
+#[test]
+fn call_reports_runtime_errors_of_the_function() {
+    let mut lox = Lox::new();
+    lox.run_source("fun fail() {\n  throw \"broken\";\n}")
+        .unwrap();
+    assert_eq!(
+        lox.call::<Value, _>("fail", ()).unwrap_err(),
+        Error::Runtime(diagnostic(2, "broken"))
+    );
+}
+
+#[test]
+fn runs_flush_their_output() {
+    let (output, error) = (OutputBuffer::new(), OutputBuffer::new());
+    let mut lox = Lox::new();
+    lox.set_output(BufWriter::new(output.clone()));
+    lox.set_error_output(BufWriter::new(error.clone()));
+    lox.run_source("print 1;\neprint(2);\nfun f() {\n  print 3;\n  -nil;\n}")
+        .unwrap();
+    assert_eq!(
+        (output.contents(), error.contents()),
+        ("1\n".into(), "2\n".into())
+    );
+
+    lox.eval_expression("f()").unwrap_err();
+    assert_eq!(output.contents(), "1\n3\n");
+    lox.call::<(), _>("f", ()).unwrap_err();
+    assert_eq!(output.contents(), "1\n3\n3\n");
+    lox.run_source("f();").unwrap_err();
+    assert_eq!(output.contents(), "1\n3\n3\n3\n");
+}
+
+#[test]
+fn imports_of_snippets_take_read_access() {
+    let source = "import \"programs/import/lib/util.lox\" as util;";
+    let mut lox = Lox::new();
+    assert_eq!(
+        lox.run_source(source).unwrap_err(),
+        Error::Runtime(diagnostic(
+            1,
+            "Permission denied: no read access to 'programs/import/lib/util.lox'."
+        ))
+    );
+
+    let output = OutputBuffer::new();
+    let mut lox = Lox::new();
+    lox.set_output(output.clone());
+    lox.set_permissions(Permissions::all());
+    lox.run_source(source).unwrap();
+    assert_eq!(output.contents(), "loading util\n");
+    assert!(matches!(lox.get_global("util"), Some(Value::Module(_))));
+}
+
+// Debug builds take much more stack per level of nesting than the default
+// threads of tests have, release builds fit 256 levels into them
+fn with_big_stack(test: impl FnOnce() + Send + 'static) {
+    std::thread::Builder::new()
+        .stack_size(64 * 1024 * 1024)
+        .spawn(test)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn deep_nesting_is_a_syntax_error() {
+    with_big_stack(|| {
+        let nested = |depth: usize| format!("var a = {}1{};", "(".repeat(depth), ")".repeat(depth));
+        let mut lox = Lox::new();
+        lox.run_source(&nested(200)).unwrap();
+        assert_eq!(lox.get_global("a"), Some(Value::Number(1.0)));
+
+        let error = lox.run_source(&nested(5000)).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Syntax(errors) if errors[0] == diagnostic(1, "Code is nested too deeply.")
+        ));
+        assert!(matches!(
+            lox.eval_expression(&format!("1{}", " + 1".repeat(5000))),
+            Err(Error::Syntax(_))
+        ));
+    });
+}
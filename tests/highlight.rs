@@ -0,0 +1,49 @@
+use std::process::Command;
+
+use lorx::{highlight_ansi, highlight_html};
+
+// The file has a comment, every kind of token, text that doesn't scan and an
+// unterminated string, the outputs were generated by `lorx highlight`
+const SOURCE: &str = include_str!("golden/highlight.lox");
+const ANSI: &str = include_str!("golden/highlight.ansi");
+const HTML: &str = include_str!("golden/highlight.html");
+
+fn lorx_highlight(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_lorx"))
+        .arg("highlight")
+        .args(args)
+        .arg("highlight.lox")
+        .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn ansi() {
+    assert_eq!(highlight_ansi(SOURCE), ANSI);
+    assert_eq!(lorx_highlight(&[]), ANSI);
+}
+
+#[test]
+fn html() {
+    assert_eq!(highlight_html(SOURCE, "highlight.lox"), HTML);
+    assert_eq!(lorx_highlight(&["--html"]), HTML);
+}
+
+#[test]
+fn errors_are_marked() {
+    assert!(ANSI.contains("\x1b[4;31m@\x1b[0m"));
+    assert!(ANSI.contains("\x1b[4;31m\"open\x1b[0m"));
+    assert!(HTML.contains("<span class=\"error\">@</span>"));
+    assert!(HTML.contains("<span class=\"error\">&quot;open\n</span>"));
+}
+
+#[test]
+fn styled_lines_can_be_split() {
+    // a string over several lines is styled a line at a time
+    let ansi = highlight_ansi("var s = \"a\nb\";");
+    let lines: Vec<&str> = ansi.lines().collect();
+    assert_eq!(lines[1], "\x1b[32mb\"\x1b[0m\x1b[36m;\x1b[0m");
+}
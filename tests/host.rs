@@ -0,0 +1,157 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use lorx::{Diagnostic, Error, HostObject, Lox, NativeFunction, Value};
+
+// A request context with a read-only path, a writable status and a method
+#[derive(Debug)]
+struct Request {
+    path: String,
+    status: Cell<f64>,
+    headers: RefCell<Vec<(String, String)>>,
+}
+
+impl HostObject for Request {
+    fn class_name(&self) -> &str {
+        "Request"
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        match name {
+            "path" => Some(Value::Text(self.path.clone())),
+            "status" => Some(Value::Number(self.status.get())),
+            _ => None,
+        }
+    }
+
+    fn set(&self, name: &str, value: Value) -> Result<(), String> {
+        match (name, value) {
+            ("status", Value::Number(status)) => {
+                self.status.set(status);
+                Ok(())
+            }
+            ("status", _) => Err("Status must be a number.".to_owned()),
+            (name, _) => Err(format!("Can't set '{}' on a request.", name)),
+        }
+    }
+
+    fn method(self: Rc<Self>, name: &str) -> Option<NativeFunction> {
+        match name {
+            "header" => Some(NativeFunction::new(
+                name,
+                move |key: String, value: String| {
+                    self.headers.borrow_mut().push((key, value));
+                },
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn request() -> Rc<Request> {
+    Rc::new(Request {
+        path: "/index".to_owned(),
+        status: Cell::new(200.0),
+        headers: Default::default(),
+    })
+}
+
+#[test]
+fn scripts_read_properties() {
+    let mut lox = Lox::new();
+    lox.set_global("request", request());
+    assert_eq!(
+        lox.eval_expression("request.path + \" \" + str(request.status)")
+            .unwrap(),
+        Value::Text("/index 200".to_owned())
+    );
+}
+
+#[test]
+fn scripts_set_properties() {
+    let request = request();
+    let mut lox = Lox::new();
+    lox.set_global("request", request.clone());
+    lox.run_source("request.status = 404;").unwrap();
+    assert_eq!(request.status.get(), 404.0);
+}
+
+#[test]
+fn rejected_sets_are_runtime_errors() {
+    let mut lox = Lox::new();
+    lox.set_global("request", request());
+    assert_eq!(
+        lox.run_source("\nrequest.path = \"/\";").unwrap_err(),
+        Error::Runtime(Diagnostic {
+            line: 2,
+            message: "Can't set 'path' on a request.".to_owned()
+        })
+    );
+    assert_eq!(
+        lox.run_source("request.status = \"ok\";").unwrap_err(),
+        Error::Runtime(Diagnostic {
+            line: 1,
+            message: "Status must be a number.".to_owned()
+        })
+    );
+}
+
+#[test]
+fn scripts_call_methods() {
+    let request = request();
+    let mut lox = Lox::new();
+    lox.set_global("request", request.clone());
+    lox.run_source(
+        "var header = request.header; header(\"a\", \"1\"); request.header(\"b\", \"2\");",
+    )
+    .unwrap();
+    assert_eq!(
+        *request.headers.borrow(),
+        vec![
+            ("a".to_owned(), "1".to_owned()),
+            ("b".to_owned(), "2".to_owned())
+        ]
+    );
+}
+
+#[test]
+fn method_arguments_are_checked() {
+    let mut lox = Lox::new();
+    lox.set_global("request", request());
+    let error = lox.run_source("request.header(\"a\", 1);").unwrap_err();
+    assert_eq!(
+        error,
+        Error::Runtime(Diagnostic {
+            line: 1,
+            message: "Expected string as argument 2 of 'header' but got number.".to_owned()
+        })
+    );
+}
+
+#[test]
+fn host_objects_look_like_instances() {
+    let mut lox = Lox::new();
+    lox.set_global("request", request());
+    assert_eq!(
+        lox.eval_expression("str(request)").unwrap(),
+        Value::Text("Request instance".to_owned())
+    );
+    assert_eq!(
+        lox.eval_expression("type(request)").unwrap(),
+        Value::Text("instance".to_owned())
+    );
+    assert_eq!(
+        lox.eval_expression("request == request").unwrap(),
+        Value::Boolean(true)
+    );
+}
+
+#[test]
+fn undefined_properties_are_runtime_errors() {
+    let mut lox = Lox::new();
+    lox.set_global("request", request());
+    assert!(matches!(
+        lox.eval_expression("request.body"),
+        Err(Error::Runtime(_))
+    ));
+}
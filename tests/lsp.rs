@@ -0,0 +1,117 @@
+use std::io::ErrorKind;
+
+mod common;
+
+use common::{bodies, frame};
+use lorx::serve_lsp;
+
+const URI: &str = "file:///greet.lox";
+
+fn did_open(text: &str) -> String {
+    frame(&format!(
+        r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{}","languageId":"lox","version":1,"text":"{}"}}}}}}"#,
+        URI,
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    ))
+}
+
+// Run a session, returning whether it was shut down and what the server wrote
+fn session(messages: &[String]) -> (bool, Vec<String>) {
+    let input = messages.concat();
+    let mut output = vec![];
+    let shutdown = serve_lsp(input.as_bytes(), &mut output).unwrap();
+    (shutdown, bodies(std::str::from_utf8(&output).unwrap()))
+}
+
+fn initialize() -> String {
+    frame(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#)
+}
+
+fn shutdown_and_exit() -> Vec<String> {
+    vec![
+        frame(r#"{"jsonrpc":"2.0","id":9,"method":"shutdown"}"#),
+        frame(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+    ]
+}
+
+#[test]
+fn definition_of_a_call() {
+    let mut messages = vec![
+        initialize(),
+        frame(r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#),
+        did_open("fun greet(name) {\n  return \"hi \" + name;\n}\nprint greet(\"you\");\n"),
+        frame(&format!(
+            r#"{{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{{"textDocument":{{"uri":"{}"}},"position":{{"line":3,"character":7}}}}}}"#,
+            URI
+        )),
+    ];
+    messages.extend(shutdown_and_exit());
+    let (shutdown, bodies) = session(&messages);
+
+    assert!(shutdown);
+    assert_eq!(bodies.len(), 4);
+    assert!(bodies[0].starts_with(r#"{"jsonrpc":"2.0","id":1,"result":{"capabilities":"#));
+    assert!(bodies[0].contains(r#""definitionProvider":true"#));
+    assert_eq!(
+        bodies[1],
+        r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///greet.lox","diagnostics":[]}}"#
+    );
+    assert_eq!(
+        bodies[2],
+        r#"{"jsonrpc":"2.0","id":2,"result":{"uri":"file:///greet.lox","range":{"start":{"line":0,"character":4},"end":{"line":0,"character":9}}}}"#
+    );
+    assert_eq!(bodies[3], r#"{"jsonrpc":"2.0","id":9,"result":null}"#);
+}
+
+#[test]
+fn syntax_errors_are_published() {
+    let mut messages = vec![initialize(), did_open("var a = 1;\nprint ;\n")];
+    messages.extend(shutdown_and_exit());
+    let (_, bodies) = session(&messages);
+
+    assert!(bodies[1].contains(r#""message":"Expect expression.""#));
+    assert!(bodies[1].contains(r#""start":{"line":1,"#));
+}
+
+#[test]
+fn invalid_escapes_are_published() {
+    let mut messages = vec![
+        initialize(),
+        did_open("print \"\\q\";\nprint \"ok\" + 1;\n"),
+    ];
+    messages.extend(shutdown_and_exit());
+    let (shutdown, bodies) = session(&messages);
+
+    assert!(shutdown);
+    assert!(bodies[1].contains(r#""message":"Invalid escape sequence.""#));
+}
+
+#[test]
+fn exit_without_shutdown() {
+    let messages = vec![initialize(), frame(r#"{"jsonrpc":"2.0","method":"exit"}"#)];
+    let (shutdown, bodies) = session(&messages);
+    assert!(!shutdown);
+    assert_eq!(bodies.len(), 1);
+}
+
+#[test]
+fn oversized_messages_are_errors() {
+    let input = "Content-Length: 999999999999\r\n\r\n";
+    let error = serve_lsp(input.as_bytes(), vec![]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn deeply_nested_messages_are_parse_errors() {
+    let messages = vec![
+        frame(&"[".repeat(1 << 20)),
+        initialize(),
+        frame(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+    ];
+    let (_, bodies) = session(&messages);
+    assert!(bodies[0].starts_with(r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"#));
+    assert!(bodies[0].contains("Too deeply nested"));
+    assert!(bodies[1].starts_with(r#"{"jsonrpc":"2.0","id":1,"result":"#));
+}
@@ -0,0 +1,59 @@
+use std::io::Cursor;
+
+use lorx::{Lox, OutputBuffer, Value};
+
+#[test]
+fn print_goes_to_the_output() {
+    let output = OutputBuffer::new();
+    let mut lox = Lox::new();
+    lox.set_output(output.clone());
+    lox.run_source("print 1 + 2;\nprint \"done\";").unwrap();
+    assert_eq!(output.contents(), "3\ndone\n");
+}
+
+#[test]
+fn output_is_kept_across_runs_until_cleared() {
+    let output = OutputBuffer::new();
+    let mut lox = Lox::new();
+    lox.set_output(output.clone());
+    lox.run_source("print \"a\";").unwrap();
+    lox.run_source("print \"b\";").unwrap();
+    assert_eq!(output.contents(), "a\nb\n");
+
+    output.clear();
+    lox.run_source("print \"c\";").unwrap();
+    assert_eq!(output.contents(), "c\n");
+}
+
+#[test]
+fn output_before_an_error_is_kept() {
+    let output = OutputBuffer::new();
+    let mut lox = Lox::new();
+    lox.set_output(output.clone());
+    assert!(lox.run_source("print \"before\";\nundefined();").is_err());
+    assert_eq!(output.contents(), "before\n");
+}
+
+#[test]
+fn eprint_goes_to_the_error_output() {
+    let output = OutputBuffer::new();
+    let error = OutputBuffer::new();
+    let mut lox = Lox::new();
+    lox.set_output(output.clone());
+    lox.set_error_output(error.clone());
+    lox.run_source("eprint(\"oops\");\nprint \"fine\";")
+        .unwrap();
+    assert_eq!(output.contents(), "fine\n");
+    assert_eq!(error.contents(), "oops\n");
+}
+
+#[test]
+fn read_line_reads_the_input() {
+    let mut lox = Lox::new();
+    lox.set_input(Cursor::new("first\nsecond\r\n"));
+    lox.run_source("var a = readLine(); var b = readLine(); var c = readLine();")
+        .unwrap();
+    assert_eq!(lox.get_global("a"), Some(Value::Text("first".to_owned())));
+    assert_eq!(lox.get_global("b"), Some(Value::Text("second".to_owned())));
+    assert_eq!(lox.get_global("c"), Some(Value::Nil));
+}